@@ -0,0 +1,489 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+#[double]
+use common::config::Config;
+#[double]
+use common::sync::GithubClient;
+use common::{interactive_io::InteractiveIO, sync::GistAccess};
+use mockall_double::double;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+#[cfg(not(test))]
+use paths as zed_paths;
+use serde::Serialize;
+#[cfg(test)]
+use test_support::zed_paths;
+use zed_extension_api::serde_json::to_string;
+
+use crate::output::{OutputFormat, Summary};
+
+const EXTENSION_ID: &str = "settings-sync";
+const LSP_BINARY_NAME: &str = "zed-settings-sync-lsp";
+const GIST_SCOPE: &str = "gist";
+const TOKEN_GUIDE_URL: &str =
+    "https://github.com/vittorius/zed-settings-sync/blob/main/docs/CREATE_GITHUB_TOKEN.md";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    Passed,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct CheckResult {
+    check: &'static str,
+    status: CheckStatus,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl CheckResult {
+    fn passed(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Passed,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(check: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Warning,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn failed(check: &'static str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Failed,
+            message: message.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn skipped(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: CheckStatus::Skipped,
+            message: message.into(),
+            hint: None,
+        }
+    }
+}
+
+/// Checks the extension setup end to end, suggesting a fix for each problem found
+pub struct Doctor<'a> {
+    io: &'a mut dyn InteractiveIO,
+    output: OutputFormat,
+    summary: Summary,
+}
+
+impl<'a> Doctor<'a> {
+    pub fn new(io: &'a mut dyn InteractiveIO, output: OutputFormat) -> Self {
+        Doctor {
+            io,
+            output,
+            summary: Summary::default(),
+        }
+    }
+
+    pub async fn run_checks(&mut self) -> Result<Summary> {
+        let config = match Config::from_settings_file() {
+            Ok(config) => {
+                self.report(&CheckResult::passed(
+                    "settings file",
+                    format!(
+                        "Extension configuration found in {}",
+                        zed_paths::settings_file().display()
+                    ),
+                ))?;
+                Some(config)
+            }
+            Err(e) => {
+                self.report(&CheckResult::failed(
+                    "settings file",
+                    e.to_string(),
+                    "Run `zed-settings-sync-cli init`, or add `gist_id` and `github_token` to \
+                     `lsp.settings-sync.initialization_options` in your Zed settings file",
+                ))?;
+                None
+            }
+        };
+
+        if let Some(config) = config {
+            match config.sync_options().and_then(|sync_options| {
+                GithubClient::new(
+                    config.gist_id().into(),
+                    config.github_token().into(),
+                    sync_options,
+                )
+            }) {
+                Ok(client) => {
+                    self.report(&check_token(&client).await)?;
+                    self.report(&check_gist(&client, config.gist_id()).await)?;
+                }
+                Err(e) => {
+                    self.report(&CheckResult::failed(
+                        "github token",
+                        e.to_string(),
+                        "Check the extension configuration in your Zed settings file",
+                    ))?;
+                    self.report(&CheckResult::skipped("gist", "No Github client"))?;
+                }
+            }
+        } else {
+            self.report(&CheckResult::skipped("github token", "No configuration"))?;
+            self.report(&CheckResult::skipped("gist", "No configuration"))?;
+        }
+
+        self.report(&check_language_server(find_in_path))?;
+        self.report(&check_config_dir_watching())?;
+
+        Ok(self.summary)
+    }
+
+    fn report(&mut self, result: &CheckResult) -> Result<()> {
+        match result.status {
+            CheckStatus::Passed | CheckStatus::Warning => self.summary.succeeded += 1,
+            CheckStatus::Failed => self.summary.failed += 1,
+            CheckStatus::Skipped => self.summary.skipped += 1,
+        }
+
+        match self.output {
+            OutputFormat::Text => {
+                let icon = match result.status {
+                    CheckStatus::Passed => "🟢",
+                    CheckStatus::Warning => "🟡",
+                    CheckStatus::Failed => "🔴",
+                    CheckStatus::Skipped => "⚪",
+                };
+                self.io
+                    .write_line(&format!("{icon} {}: {}", result.check, result.message))?;
+                if let Some(hint) = &result.hint {
+                    self.io.write_line(&format!("   hint: {hint}"))?;
+                }
+            }
+            OutputFormat::Json => self.io.write_line(&to_string(result)?)?,
+        }
+
+        Ok(())
+    }
+}
+
+async fn check_token(client: &GithubClient) -> CheckResult {
+    const CHECK: &str = "github token";
+
+    match client.token_scopes().await {
+        Ok(Some(scopes)) if scopes.iter().any(|scope| scope == GIST_SCOPE) => {
+            CheckResult::passed(CHECK, "Token has the gist scope")
+        }
+        Ok(Some(scopes)) => CheckResult::failed(
+            CHECK,
+            format!(
+                "Token is missing the gist scope, it has: {}",
+                scopes.join(", ")
+            ),
+            format!("Create a token with the gist scope, see {TOKEN_GUIDE_URL}"),
+        ),
+        Ok(None) => CheckResult::warning(
+            CHECK,
+            "Token scopes can't be checked, probably it's a fine-grained token",
+            "Make sure the token has read and write access to gists",
+        ),
+        Err(e) => CheckResult::failed(
+            CHECK,
+            format!("Token was rejected: {e}"),
+            format!("Check that the token hasn't expired or been revoked, see {TOKEN_GUIDE_URL}"),
+        ),
+    }
+}
+
+async fn check_gist(client: &GithubClient, gist_id: &str) -> CheckResult {
+    const CHECK: &str = "gist";
+
+    match client.gist_access().await {
+        Ok(GistAccess::Writable) => {
+            CheckResult::passed(CHECK, format!("Gist {gist_id} is writable"))
+        }
+        Ok(GistAccess::ReadOnly { owner }) => CheckResult::failed(
+            CHECK,
+            format!("Gist {gist_id} belongs to {owner}, the token can't update it"),
+            "Use a gist owned by the token's user, or run `zed-settings-sync-cli init` to create one",
+        ),
+        Ok(GistAccess::NotFound) => CheckResult::failed(
+            CHECK,
+            format!("Gist {gist_id} not found"),
+            "Check `gist_id` in your Zed settings file, or run `zed-settings-sync-cli init` to create a new gist",
+        ),
+        Err(e) => CheckResult::failed(
+            CHECK,
+            format!("Failed to get gist {gist_id}: {e}"),
+            "Check your network connection and the Github token",
+        ),
+    }
+}
+
+// the PATH lookup is given to keep the check independent of the machine it runs on
+fn check_language_server(find_binary: fn(&str) -> Option<PathBuf>) -> CheckResult {
+    const CHECK: &str = "language server";
+
+    let extensions_dir = zed_paths::extensions_dir();
+    let Some(extension_version) = installed_extension_version(&extensions_dir) else {
+        return CheckResult::failed(
+            CHECK,
+            "Settings Sync extension is not installed",
+            "Install it with `zed: extensions` in Zed",
+        );
+    };
+
+    // the extension prefers a binary found in PATH over the downloaded one
+    if let Some(path) = find_binary(LSP_BINARY_NAME) {
+        return CheckResult::warning(
+            CHECK,
+            format!(
+                "Using {} from PATH, its version can't be checked",
+                path.display()
+            ),
+            format!("Make sure its version is {extension_version}, or remove it from PATH"),
+        );
+    }
+
+    let work_dir = extensions_dir.join("work").join(EXTENSION_ID);
+    let lsp_versions = downloaded_lsp_versions(&work_dir);
+    if lsp_versions.is_empty() {
+        return CheckResult::warning(
+            CHECK,
+            "Language server hasn't been downloaded yet",
+            "Open your Zed settings file, the extension downloads the language server on start",
+        );
+    }
+
+    if lsp_versions.contains(&extension_version) {
+        CheckResult::passed(
+            CHECK,
+            format!("Language server version matches the extension ({extension_version})"),
+        )
+    } else {
+        CheckResult::failed(
+            CHECK,
+            format!(
+                "Language server {} doesn't match the extension {extension_version}",
+                lsp_versions.join(", ")
+            ),
+            format!(
+                "Remove {} and restart Zed to download the matching language server",
+                work_dir.display()
+            ),
+        )
+    }
+}
+
+fn check_config_dir_watching() -> CheckResult {
+    const CHECK: &str = "config dir watching";
+
+    let config_dir = zed_paths::config_dir();
+    // watching the same way as the language server does
+    let result = RecommendedWatcher::new(|_| {}, notify::Config::default())
+        .and_then(|mut watcher| watcher.watch(&config_dir, RecursiveMode::Recursive));
+
+    match result {
+        Ok(()) => CheckResult::passed(CHECK, format!("{} can be watched", config_dir.display())),
+        Err(e) => CheckResult::failed(
+            CHECK,
+            format!("Failed to watch {}: {e}", config_dir.display()),
+            if cfg!(target_os = "linux") {
+                "Check that the directory exists and is readable, or raise the inotify watch limit \
+                 with `sudo sysctl fs.inotify.max_user_watches=524288`"
+            } else {
+                "Check that the directory exists and is readable"
+            },
+        ),
+    }
+}
+
+fn installed_extension_version(extensions_dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(
+        extensions_dir
+            .join("installed")
+            .join(EXTENSION_ID)
+            .join("extension.toml"),
+    )
+    .ok()?;
+
+    // the manifest is flat enough to not need a TOML parser for this
+    manifest.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "version").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+// the extension downloads the language server into "zed-settings-sync-lsp-<release version>" dirs
+fn downloaded_lsp_versions(work_dir: &Path) -> Vec<String> {
+    let prefix = format!("{LSP_BINARY_NAME}-");
+    let mut versions: Vec<_> = fs::read_dir(work_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().to_string_lossy().into_owned();
+            let version = name.strip_prefix(&prefix)?;
+            Some(version.trim_start_matches('v').to_string())
+        })
+        .collect();
+    versions.sort();
+
+    versions
+}
+
+fn find_in_path(binary_name: &str) -> Option<PathBuf> {
+    let file_name = format!("{binary_name}{}", env::consts::EXE_SUFFIX);
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub Doctor {
+        pub fn new<'a>(io: &'a mut dyn InteractiveIO, output: OutputFormat) -> Self;
+        pub async fn run_checks(&mut self) -> Result<Summary>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use common::{
+        config::MockConfig,
+        interactive_io::MockInteractiveIO,
+        sync::{MockGithubClient, SyncOptions},
+    };
+    use test_support::zed_data_dir;
+    use zed_extension_api::serde_json::{Value, from_str, json};
+
+    use super::*;
+
+    #[test]
+    fn test_language_server_version_is_compared_with_extension() -> Result<()> {
+        let extensions_dir = zed_data_dir().child("extensions");
+        extensions_dir
+            .child("installed/settings-sync/extension.toml")
+            .write_str("id = \"settings-sync\"\nversion = \"0.1.1\"\n")?;
+        extensions_dir
+            .child("work/settings-sync/zed-settings-sync-lsp-v0.1.0")
+            .create_dir_all()?;
+
+        assert_eq!(
+            installed_extension_version(extensions_dir.path()).as_deref(),
+            Some("0.1.1")
+        );
+        assert_eq!(check_language_server(|_| None).status, CheckStatus::Failed);
+
+        extensions_dir
+            .child("work/settings-sync/zed-settings-sync-lsp-v0.1.1")
+            .create_dir_all()?;
+
+        assert_eq!(check_language_server(|_| None).status, CheckStatus::Passed);
+        assert_eq!(
+            check_language_server(|_| Some(PathBuf::from("/usr/local/bin/zed-settings-sync-lsp")))
+                .status,
+            CheckStatus::Warning
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_each_check_is_reported_with_a_hint() -> Result<()> {
+        let config_ctx = MockConfig::from_settings_file_context();
+        config_ctx.expect().returning(|| {
+            let mut mock_config = MockConfig::default();
+            mock_config
+                .expect_gist_id()
+                .return_const("1234567890".to_string());
+            mock_config
+                .expect_github_token()
+                .return_const("gho_token".to_string());
+            mock_config
+                .expect_sync_options()
+                .returning(|| Ok(SyncOptions::default()));
+            Ok(mock_config)
+        });
+
+        let gh_ctx = MockGithubClient::new_context();
+        gh_ctx
+            .expect()
+            .returning(|_gist_id, _github_token, _options| {
+                let mut mock_client = MockGithubClient::default();
+                mock_client
+                    .expect_token_scopes()
+                    .returning(|| Ok(Some(vec!["repo".to_string()])));
+                mock_client
+                    .expect_gist_access()
+                    .returning(|| Ok(GistAccess::NotFound));
+                Ok(mock_client)
+            });
+
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let mut mock_io = MockInteractiveIO::default();
+        let written_lines = Arc::clone(&lines);
+        mock_io.expect_write_line().returning(move |line| {
+            written_lines.lock().unwrap().push(from_str::<Value>(line)?);
+            Ok(())
+        });
+
+        let mut doctor = Doctor::new(&mut mock_io, OutputFormat::Json);
+        let summary = doctor.run_checks().await?;
+
+        let results = lines.lock().unwrap();
+        assert_eq!(results[0]["check"], "settings file");
+        assert_eq!(results[0]["status"], "passed");
+        assert_eq!(
+            results[1],
+            json!({
+                "check": "github token",
+                "status": "failed",
+                "message": "Token is missing the gist scope, it has: repo",
+                "hint": format!("Create a token with the gist scope, see {TOKEN_GUIDE_URL}")
+            })
+        );
+        assert_eq!(
+            results[2],
+            json!({
+                "check": "gist",
+                "status": "failed",
+                "message": "Gist 1234567890 not found",
+                "hint": "Check `gist_id` in your Zed settings file, or run `zed-settings-sync-cli init` to create a new gist"
+            })
+        );
+        assert_eq!(results.len(), 5);
+
+        // the extension isn't installed in the test Zed data dir
+        let failed_checks: Vec<_> = results
+            .iter()
+            .filter(|result| result["status"] == "failed")
+            .map(|result| result["check"].as_str().unwrap())
+            .collect();
+        assert_eq!(failed_checks, ["github token", "gist", "language server"]);
+        assert_eq!(summary.failed, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+};
+
+use anyhow::Result;
+use common::{
+    interactive_io::InteractiveIO,
+    sync::{CURRENT_OS, Client, materialize_os_sections},
+};
+use jsonc_parser::{ParseOptions, parse_to_serde_value};
+#[cfg(not(test))]
+use paths as zed_paths;
+use serde::Serialize;
+#[cfg(test)]
+use test_support::zed_paths;
+use zed_extension_api::serde_json::{Value, to_string_pretty};
+
+#[derive(Debug, PartialEq, Serialize)]
+struct FileDiff {
+    file_name: String,
+    #[serde(flatten)]
+    state: DiffState,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum DiffState {
+    MissingLocally,
+    Unchanged,
+    Changed { changes: Vec<KeyChange> },
+    Error { error: String },
+}
+
+/// A change that loading the remote file would make to the local one, keys are JSON pointers
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum KeyChange {
+    Added {
+        key: String,
+        value: Value,
+    },
+    Removed {
+        key: String,
+        value: Value,
+    },
+    Changed {
+        key: String,
+        local: Value,
+        remote: Value,
+    },
+}
+
+impl KeyChange {
+    fn key(&self) -> &str {
+        match self {
+            KeyChange::Added { key, .. }
+            | KeyChange::Removed { key, .. }
+            | KeyChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl Display for KeyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyChange::Added { key, value } => write!(f, "+ {key}: {value}"),
+            KeyChange::Removed { key, value } => write!(f, "- {key}: {value}"),
+            KeyChange::Changed { key, local, remote } => {
+                write!(f, "~ {key}: {local} -> {remote}")
+            }
+        }
+    }
+}
+
+pub struct FileDiffer<'a> {
+    client: &'a dyn Client,
+    io: &'a mut dyn InteractiveIO,
+    json: bool,
+}
+
+impl<'a> FileDiffer<'a> {
+    pub fn new(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, json: bool) -> Self {
+        FileDiffer { client, io, json }
+    }
+
+    pub async fn diff_files(&mut self) -> Result<()> {
+        let mut diffs = Vec::new();
+
+        for file_load_result in self.client.load_files().await? {
+            diffs.push(match file_load_result {
+                Ok((file_name, content)) => diff_file(file_name, &content),
+                Err(e) => FileDiff {
+                    file_name: e.file_name().to_string(),
+                    state: DiffState::Error {
+                        error: e.to_string(),
+                    },
+                },
+            });
+        }
+
+        if self.json {
+            self.io.write_line(&to_string_pretty(&diffs)?)?;
+        } else {
+            for diff in &diffs {
+                self.write_diff(diff)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_diff(&mut self, diff: &FileDiff) -> Result<()> {
+        let file_name = &diff.file_name;
+
+        match &diff.state {
+            DiffState::MissingLocally => self
+                .io
+                .write_line(&format!("{file_name}: missing locally, would be created"))?,
+            DiffState::Unchanged => self.io.write_line(&format!("{file_name}: unchanged"))?,
+            DiffState::Changed { changes } => {
+                self.io.write_line(&format!("{file_name}:"))?;
+                for change in changes {
+                    self.io.write_line(&format!("  {change}"))?;
+                }
+            }
+            DiffState::Error { error } => self.io.write_line(&format!("🔴 {error}"))?,
+        }
+
+        Ok(())
+    }
+}
+
+fn diff_file(file_name: String, remote_content: &str) -> FileDiff {
+    let state =
+        compare_with_local(&file_name, remote_content).unwrap_or_else(|e| DiffState::Error {
+            error: format!("Error comparing file {file_name}: {e}"),
+        });
+
+    FileDiff { file_name, state }
+}
+
+fn compare_with_local(file_name: &str, remote_content: &str) -> Result<DiffState> {
+    let file_path = zed_paths::config_dir().join(file_name);
+    if !file_path.try_exists()? {
+        return Ok(DiffState::MissingLocally);
+    }
+
+    // comparing with the remote file the way it would be written by `load`
+    let local = parse_json(&fs::read_to_string(file_path)?)?;
+    let remote = parse_json(&materialize_os_sections(remote_content, CURRENT_OS)?)?;
+    let changes = diff_values(&local, &remote);
+
+    if changes.is_empty() {
+        Ok(DiffState::Unchanged)
+    } else {
+        Ok(DiffState::Changed { changes })
+    }
+}
+
+fn parse_json(content: &str) -> Result<Value> {
+    Ok(parse_to_serde_value(content, &ParseOptions::default())?.unwrap_or(Value::Null))
+}
+
+fn diff_values(local: &Value, remote: &Value) -> Vec<KeyChange> {
+    let mut remote_leaves = flatten(remote);
+    let mut changes = Vec::new();
+
+    for (key, local_value) in flatten(local) {
+        match remote_leaves.remove(&key) {
+            None => changes.push(KeyChange::Removed {
+                key,
+                value: local_value,
+            }),
+            Some(remote_value) if remote_value != local_value => {
+                changes.push(KeyChange::Changed {
+                    key,
+                    local: local_value,
+                    remote: remote_value,
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    changes.extend(
+        remote_leaves
+            .into_iter()
+            .map(|(key, value)| KeyChange::Added { key, value }),
+    );
+    changes.sort_by(|a, b| a.key().cmp(b.key()));
+
+    changes
+}
+
+// maps JSON pointers of scalar values and empty containers to the values themselves
+fn flatten(value: &Value) -> BTreeMap<String, Value> {
+    let mut leaves = BTreeMap::new();
+    flatten_into(value, String::new(), &mut leaves);
+
+    leaves
+}
+
+fn flatten_into(value: &Value, pointer: String, leaves: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let segment = key.replace('~', "~0").replace('/', "~1");
+                flatten_into(value, format!("{pointer}/{segment}"), leaves);
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            for (index, value) in values.iter().enumerate() {
+                flatten_into(value, format!("{pointer}/{index}"), leaves);
+            }
+        }
+        _ => {
+            leaves.insert(pointer, value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub FileDiffer {
+        pub fn new<'a>(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, json: bool) -> Self;
+        pub async fn diff_files(&mut self) -> Result<()>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use common::{
+        interactive_io::MockInteractiveIO,
+        sync::{Error, FileError, MockGithubClient},
+    };
+    use mockall::{Sequence, predicate};
+    use test_support::zed_config_dir;
+    use zed_extension_api::serde_json::{from_str, json};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_changes_are_listed_per_file() -> Result<()> {
+        zed_config_dir().child("settings.json").write_str(
+            r#"{
+                // local settings
+                "theme": "One Dark",
+                "tab_size": 2,
+                "vim_mode": true
+            }"#,
+        )?;
+        zed_config_dir()
+            .child("tasks.json")
+            .write_str(r#"[{ "label": "test", "command": "cargo test" }]"#)?;
+
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_load_files()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Ok(Box::new(
+                    [
+                        Ok((
+                            "settings.json".to_string(),
+                            r#"{ "theme": "Ayu Dark", "tab_size": 2, "terminal": { "shell": "system" } }"#
+                                .to_string(),
+                        )),
+                        Ok((
+                            "tasks.json".to_string(),
+                            "[\n  { \"label\": \"test\", \"command\": \"cargo test\" }\n]".to_string(),
+                        )),
+                        Ok(("keymap.json".to_string(), "[]".to_string())),
+                    ]
+                    .into_iter(),
+                ))
+            });
+
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            "settings.json:",
+            r#"  + /terminal/shell: "system""#,
+            r#"  ~ /theme: "One Dark" -> "Ayu Dark""#,
+            "  - /vim_mode: true",
+            "tasks.json: unchanged",
+            "keymap.json: missing locally, would be created",
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut file_differ = FileDiffer::new(&mock_client, &mut mock_io, false);
+        file_differ.diff_files().await
+    }
+
+    #[tokio::test]
+    async fn test_json_output() -> Result<()> {
+        zed_config_dir()
+            .child("keymap.json")
+            .write_str(r#"[{ "bindings": { "cmd-/": "editor::ToggleComments" } }]"#)?;
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client.expect_load_files().returning(|| {
+            Ok(Box::new(
+                [
+                    Ok((
+                        "keymap.json".to_string(),
+                        r#"[{ "bindings": { "cmd-/": "vim::ToggleComments" } }]"#.to_string(),
+                    )),
+                    Err(FileError::from_error(
+                        "debug.json",
+                        Error::UnhandledInternal("Unhandled internal error".to_string()),
+                    )),
+                ]
+                .into_iter(),
+            ))
+        });
+
+        let expected = json!([
+            {
+                "file_name": "keymap.json",
+                "status": "changed",
+                "changes": [
+                    {
+                        "kind": "changed",
+                        "key": "/0/bindings/cmd-~1",
+                        "local": "editor::ToggleComments",
+                        "remote": "vim::ToggleComments"
+                    }
+                ]
+            },
+            {
+                "file_name": "debug.json",
+                "status": "error",
+                "error": "Error syncing file debug.json: Unhandled internal error from underlying client library: Unhandled internal error"
+            }
+        ]);
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .withf(move |output| from_str::<Value>(output).is_ok_and(|value| value == expected))
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut file_differ = FileDiffer::new(&mock_client, &mut mock_io, true);
+        file_differ.diff_files().await
+    }
+
+    #[test]
+    fn test_empty_containers_are_compared_as_values() {
+        assert_eq!(
+            diff_values(&json!({ "languages": {} }), &json!({ "languages": [] })),
+            vec![KeyChange::Changed {
+                key: "/languages".to_string(),
+                local: json!({}),
+                remote: json!([]),
+            }]
+        );
+    }
+}
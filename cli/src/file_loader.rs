@@ -1,39 +1,141 @@
-use std::fs;
+use std::{collections::HashSet, path::Path};
 
 use anyhow::Result;
-use common::{interactive_io::InteractiveIO, sync::Client};
+use common::{
+    interactive_io::InteractiveIO,
+    sync::{BackupStore, Client, FileFilter, FileResult, LocalFileWriter},
+};
 #[cfg(not(test))]
 use paths as zed_paths;
 #[cfg(test)]
 use test_support::zed_paths;
 
+use crate::output::{EventReporter, FileEvent, OutputFormat, SkipReason, Summary};
+
+/// Files requested to be loaded from a gist, all of them if no file names are given
+#[derive(Debug, Default)]
+pub struct FileSelection {
+    file_names: Vec<String>,
+    exclude: FileFilter,
+    // the include/exclude rules of the configuration, to tell excluded files from missing ones
+    synced_files: FileFilter,
+}
+
+impl FileSelection {
+    pub fn new(file_names: Vec<String>, exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            file_names,
+            exclude: FileFilter::new(&[], exclude)?,
+            synced_files: FileFilter::default(),
+        })
+    }
+
+    #[must_use]
+    pub fn with_synced_files(self, synced_files: FileFilter) -> Self {
+        Self {
+            synced_files,
+            ..self
+        }
+    }
+
+    fn includes(&self, file_name: &str) -> bool {
+        (self.file_names.is_empty() || self.file_names.iter().any(|name| name == file_name))
+            && self.exclude.matches(Path::new(file_name))
+    }
+}
+
 pub struct FileLoader<'a> {
     client: &'a dyn Client,
     io: &'a mut dyn InteractiveIO,
     force: bool,
+    selection: FileSelection,
+    writer: LocalFileWriter,
+    reporter: EventReporter,
 }
 
 impl<'a> FileLoader<'a> {
-    pub fn new(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, force: bool) -> Self {
-        FileLoader { client, io, force }
+    pub fn new(
+        client: &'a dyn Client,
+        io: &'a mut dyn InteractiveIO,
+        force: bool,
+        selection: FileSelection,
+        backups: BackupStore,
+        output: OutputFormat,
+    ) -> Self {
+        FileLoader {
+            client,
+            io,
+            force,
+            selection,
+            writer: LocalFileWriter::new(&zed_paths::config_dir(), &zed_paths::data_dir(), backups),
+            reporter: EventReporter::new(output),
+        }
     }
 
-    pub async fn load_files(&mut self) -> Result<()> {
-        for file_load_result in self.client.load_files().await? {
-            match file_load_result {
-                Ok((file_name, content)) => self.process_loaded_file(file_name, content)?,
-                Err(e) => self.io.write_line(&format!("🔴 {e}"))?,
+    pub async fn load_files(&mut self) -> Result<Summary> {
+        // only logged with the loaded files, so failing to get it doesn't prevent loading
+        let remote_revision = self.client.remote_revision().await.ok().flatten();
+        let mut remote_file_names = HashSet::new();
+        let selected_files: Vec<_> = self
+            .client
+            .load_files()
+            .await?
+            .inspect(|result| {
+                remote_file_names.insert(result_file_name(result).to_string());
+            })
+            .filter(|result| self.selection.includes(result_file_name(result)))
+            .collect();
+
+        for file_load_result in selected_files {
+            let event = match file_load_result {
+                Ok((file_name, content)) => {
+                    self.process_loaded_file(file_name, content, remote_revision.clone())?
+                }
+                Err(e) => {
+                    self.writer
+                        .log_failure(e.file_name(), e.error(), remote_revision.clone());
+                    FileEvent::from(&e)
+                }
+            };
+            self.reporter.report(self.io, &event)?;
+        }
+
+        for file_name in &self.selection.file_names {
+            if !remote_file_names.contains(file_name) {
+                let reason = if self.selection.synced_files.matches(Path::new(file_name)) {
+                    SkipReason::NotFound
+                } else {
+                    SkipReason::Excluded
+                };
+                self.reporter.report(
+                    self.io,
+                    &FileEvent::Skipped {
+                        file_name: file_name.clone(),
+                        reason,
+                    },
+                )?;
             }
         }
 
-        Ok(())
+        Ok(self.reporter.summary())
     }
 
     #[allow(clippy::needless_pass_by_value)]
-    fn process_loaded_file(&mut self, file_name: String, content: String) -> Result<()> {
-        let file_path = zed_paths::config_dir().join(&file_name);
+    fn process_loaded_file(
+        &mut self,
+        file_name: String,
+        content: String,
+        remote_revision: Option<String>,
+    ) -> Result<FileEvent> {
+        if self.writer.file_path(&file_name).exists() && !self.force {
+            // a prompt would break machine-readable output
+            if self.reporter.format() == OutputFormat::Json {
+                return Ok(FileEvent::Skipped {
+                    file_name,
+                    reason: SkipReason::Exists,
+                });
+            }
 
-        if file_path.exists() && !self.force {
             self.io
                 .write(&format!("🟡 {file_name} exists, overwrite (y/n)? "))?;
 
@@ -43,24 +145,31 @@ impl<'a> FileLoader<'a> {
             if answer.trim().to_lowercase().starts_with('y') {
                 self.io.write_line(&format!("Overwriting {file_name}..."))?;
             } else {
-                self.io.write_line(&format!("Skipping {file_name}"))?;
-                return Ok(());
+                return Ok(FileEvent::Skipped {
+                    file_name,
+                    reason: SkipReason::Declined,
+                });
             }
         }
 
-        fs::write(file_path, content)?;
+        self.writer.write(&file_name, &content, remote_revision)?;
 
-        self.io.write_line(&format!("Written {file_name}"))?;
+        Ok(FileEvent::Written { file_name })
+    }
+}
 
-        Ok(())
+fn result_file_name(result: &FileResult) -> &str {
+    match result {
+        Ok((file_name, _)) => file_name,
+        Err(e) => e.file_name(),
     }
 }
 
 #[cfg(test)]
 mockall::mock! {
     pub FileLoader {
-        pub fn new<'a>(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, force: bool) -> Self;
-        pub async fn load_files(&mut self) -> Result<()>;
+        pub fn new<'a>(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, force: bool, selection: FileSelection, backups: BackupStore, output: OutputFormat) -> Self;
+        pub async fn load_files(&mut self) -> Result<Summary>;
     }
 }
 
@@ -72,7 +181,10 @@ mod tests {
     use assert_fs::prelude::*;
     use common::{
         interactive_io::MockInteractiveIO,
-        sync::{Error, FileError, MockGithubClient},
+        sync::{
+            Error, FileError, MockGithubClient, SyncDirection, SyncEventFilter, SyncEventLog,
+            SyncStateStore, content_hash,
+        },
     };
     use mockall::{Sequence, predicate};
     use test_support::zed_config_dir;
@@ -84,6 +196,9 @@ mod tests {
         let mut seq = Sequence::new();
 
         let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
         mock_client
             .expect_load_files()
             .in_sequence(&mut seq)
@@ -101,11 +216,31 @@ mod tests {
             .returning(|_| Ok(()))
             .once();
 
-        let mut file_loader = FileLoader::new(&mock_client, &mut mock_io, false);
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
         file_loader.load_files().await?;
 
         zed_config_dir().child("tasks.json").assert("content");
 
+        let state = SyncStateStore::new(&zed_paths::data_dir()).load()?;
+        assert_eq!(
+            state
+                .file("tasks.json")
+                .map(|file| file.content_hash.as_str()),
+            Some(content_hash("content").as_str())
+        );
+
+        let events = SyncEventLog::new(&zed_paths::data_dir()).query(&SyncEventFilter::default())?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].direction, SyncDirection::Load);
+        assert_eq!(events[0].remote_revision.as_deref(), Some("abc123"));
+
         Ok(())
     }
 
@@ -118,6 +253,9 @@ mod tests {
         let mut seq = Sequence::new();
 
         let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
         mock_client
             .expect_load_files()
             .in_sequence(&mut seq)
@@ -160,13 +298,24 @@ mod tests {
             .returning(|_| Ok(()))
             .once();
 
-        let mut file_loader = FileLoader::new(&mock_client, &mut mock_io, false);
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
         file_loader.load_files().await?;
 
         zed_config_dir()
             .child("settings.json")
             .assert("{ \"key\": 1 }");
 
+        let snapshots = BackupStore::new(&zed_paths::data_dir(), 0).snapshots()?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].file_names, ["settings.json"]);
+
         Ok(())
     }
 
@@ -177,6 +326,9 @@ mod tests {
         let mut seq = Sequence::new();
 
         let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
         mock_client
             .expect_load_files()
             .in_sequence(&mut seq)
@@ -213,7 +365,14 @@ mod tests {
             .returning(|_| Ok(()))
             .once();
 
-        let mut file_loader = FileLoader::new(&mock_client, &mut mock_io, false);
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
         file_loader.load_files().await?;
 
         zed_config_dir().child("keymap.json").assert("{}");
@@ -230,6 +389,9 @@ mod tests {
         let mut seq = Sequence::new();
 
         let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
         mock_client
             .expect_load_files()
             .in_sequence(&mut seq)
@@ -247,7 +409,14 @@ mod tests {
             .returning(|_| Ok(()))
             .once();
 
-        let mut file_loader = FileLoader::new(&mock_client, &mut mock_io, true);
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            true,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
         file_loader.load_files().await?;
 
         zed_config_dir()
@@ -257,11 +426,60 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_sections_for_other_os_are_emptied() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
+        mock_client
+            .expect_load_files()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Ok(Box::new(
+                    [Ok((
+                        "keymap.json".to_string(),
+                        "[\n  // @os plan9\n  { \"bindings\": {} },\n  // @end-os\n]\n".to_string(),
+                    ))]
+                    .into_iter(),
+                ))
+            });
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .in_sequence(&mut seq)
+            .with(predicate::eq("Written keymap.json"))
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            true,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
+        file_loader.load_files().await?;
+
+        zed_config_dir()
+            .child("keymap.json")
+            .assert("[\n  // @os plan9\n  // @end-os\n]\n");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_file_error_reporting() -> Result<()> {
         let mut seq = Sequence::new();
 
         let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
         let build_error = || {
             FileError::from_error(
                 "tasks.json",
@@ -282,7 +500,148 @@ mod tests {
             .returning(|_| Ok(()))
             .once();
 
-        let mut file_loader = FileLoader::new(&mock_client, &mut mock_io, false);
-        file_loader.load_files().await
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
+        let summary = file_loader.load_files().await?;
+
+        assert_eq!(
+            summary,
+            Summary {
+                succeeded: 0,
+                skipped: 0,
+                failed: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_existing_file_is_skipped_without_prompt_in_json_mode() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("{}")?;
+
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
+        mock_client
+            .expect_load_files()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Ok(Box::new(
+                    [
+                        Ok(("keymap.json".to_string(), "[]".to_string())),
+                        Ok(("tasks.json".to_string(), "[]".to_string())),
+                    ]
+                    .into_iter(),
+                ))
+            });
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io.expect_read_line().never();
+        for line in [
+            r#"{"event":"skipped","file_name":"keymap.json","reason":"exists"}"#,
+            r#"{"event":"written","file_name":"tasks.json"}"#,
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Json,
+        );
+        let summary = file_loader.load_files().await?;
+
+        assert_eq!(
+            summary,
+            Summary {
+                succeeded: 1,
+                skipped: 1,
+                failed: 0,
+            }
+        );
+        zed_config_dir().child("keymap.json").assert("{}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_selected_files_are_written() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
+        mock_client
+            .expect_load_files()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Ok(Box::new(
+                    [
+                        Ok(("keymap.json".to_string(), "[]".to_string())),
+                        Ok(("settings.json".to_string(), "{}".to_string())),
+                        Ok(("tasks.json".to_string(), "[]".to_string())),
+                    ]
+                    .into_iter(),
+                ))
+            });
+
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            "Written keymap.json",
+            "🟡 snippets.json not found in the gist",
+            "🟡 Skipping debug.json, it's excluded from sync",
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let selection = FileSelection::new(
+            vec![
+                "keymap.json".to_string(),
+                "tasks.json".to_string(),
+                "snippets.json".to_string(),
+                "debug.json".to_string(),
+            ],
+            &["tasks.*".to_string()],
+        )?
+        .with_synced_files(FileFilter::new(&[], &["debug.json".to_string()])?);
+        let mut file_loader = FileLoader::new(
+            &mock_client,
+            &mut mock_io,
+            false,
+            selection,
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        );
+        file_loader.load_files().await?;
+
+        zed_config_dir().child("keymap.json").assert("[]");
+        assert!(!zed_config_dir().child("settings.json").path().exists());
+        assert!(!zed_config_dir().child("tasks.json").path().exists());
+
+        Ok(())
     }
 }
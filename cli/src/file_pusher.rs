@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use common::{
+    interactive_io::InteractiveIO,
+    sync::{CURRENT_OS, Client, FileFilter, LocalFileData, materialize_os_sections},
+};
+
+use crate::{
+    local_files::collect_syncable_files,
+    output::{EventReporter, FileEvent, OutputFormat, PlannedChange, Summary},
+};
+
+pub struct FilePusher<'a> {
+    client: &'a dyn Client,
+    io: &'a mut dyn InteractiveIO,
+    file_filter: FileFilter,
+    dry_run: bool,
+    reporter: EventReporter,
+}
+
+impl<'a> FilePusher<'a> {
+    pub fn new(
+        client: &'a dyn Client,
+        io: &'a mut dyn InteractiveIO,
+        file_filter: FileFilter,
+        dry_run: bool,
+        output: OutputFormat,
+    ) -> Self {
+        FilePusher {
+            client,
+            io,
+            file_filter,
+            dry_run,
+            reporter: EventReporter::new(output),
+        }
+    }
+
+    pub async fn push_files(&mut self) -> Result<Summary> {
+        let local_files = collect_syncable_files(&self.file_filter)?;
+
+        if self.dry_run {
+            return self.report_changes(local_files).await;
+        }
+
+        for data in local_files {
+            let file_name = data.filename.clone();
+            let event = match self.client.sync_file(data).await {
+                Ok(()) => FileEvent::Pushed { file_name },
+                Err(e) => FileEvent::from(&e),
+            };
+            self.reporter.report(self.io, &event)?;
+        }
+
+        Ok(self.reporter.summary())
+    }
+
+    async fn report_changes(&mut self, local_files: Vec<LocalFileData>) -> Result<Summary> {
+        let mut remote_files = BTreeMap::new();
+        for file_load_result in self.client.load_files().await? {
+            match file_load_result {
+                Ok((file_name, content)) => {
+                    remote_files.insert(file_name, content);
+                }
+                Err(e) => self.reporter.report(self.io, &FileEvent::from(&e))?,
+            }
+        }
+
+        for data in local_files {
+            let change = match remote_files.get(&data.filename) {
+                None => PlannedChange::Create,
+                Some(content) if materialize_os_sections(content, CURRENT_OS)? != data.body => {
+                    PlannedChange::Update
+                }
+                Some(_) => PlannedChange::None,
+            };
+            self.reporter.report(
+                self.io,
+                &FileEvent::Planned {
+                    file_name: data.filename,
+                    change,
+                },
+            )?;
+        }
+
+        Ok(self.reporter.summary())
+    }
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub FilePusher {
+        pub fn new<'a>(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, file_filter: FileFilter, dry_run: bool, output: OutputFormat) -> Self;
+        pub async fn push_files(&mut self) -> Result<Summary>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use common::{
+        interactive_io::MockInteractiveIO,
+        sync::{Error, FileError, MockGithubClient},
+    };
+    use mockall::{Sequence, predicate};
+    use test_support::zed_config_dir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_syncable_files_are_pushed() -> Result<()> {
+        zed_config_dir().child("tasks.json").write_str("[]")?;
+        zed_config_dir().child("debug.json").write_str("[]")?;
+        zed_config_dir().child("notes.txt").write_str("hello")?;
+
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_sync_file()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(LocalFileData::new(
+                zed_config_dir().child("tasks.json").path().to_path_buf(),
+                "[]".to_string(),
+            )?))
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .in_sequence(&mut seq)
+            .with(predicate::eq("Pushed tasks.json"))
+            .returning(|_| Ok(()))
+            .once();
+
+        let file_filter = FileFilter::new(&[], &["debug.json".to_string()])?;
+        let mut file_pusher = FilePusher::new(
+            &mock_client,
+            &mut mock_io,
+            file_filter,
+            false,
+            OutputFormat::Text,
+        );
+        file_pusher.push_files().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_error_reporting() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        let build_error = || {
+            FileError::from_error(
+                "keymap.json",
+                Error::UnhandledInternal("Unhandled internal error".to_string()),
+            )
+        };
+        mock_client
+            .expect_sync_file()
+            .in_sequence(&mut seq)
+            .returning(move |_| Err(build_error()))
+            .once();
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(format!("🔴 {}", build_error())))
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut file_pusher = FilePusher::new(
+            &mock_client,
+            &mut mock_io,
+            FileFilter::default(),
+            false,
+            OutputFormat::Text,
+        );
+        let summary = file_pusher.push_files().await?;
+
+        assert_eq!(summary.failed, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_lists_changes_without_pushing() -> Result<()> {
+        zed_config_dir().child("debug.json").write_str("[]")?;
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+        zed_config_dir()
+            .child("tasks.json")
+            .write_str("[{ \"label\": \"new\" }]")?;
+
+        let mut seq = Sequence::new();
+
+        let mut mock_client = MockGithubClient::default();
+        mock_client.expect_sync_file().never();
+        mock_client
+            .expect_load_files()
+            .in_sequence(&mut seq)
+            .returning(|| {
+                Ok(Box::new(
+                    [
+                        Ok(("keymap.json".to_string(), "[]".to_string())),
+                        Ok((
+                            "tasks.json".to_string(),
+                            "[{ \"label\": \"old\" }]".to_string(),
+                        )),
+                    ]
+                    .into_iter(),
+                ))
+            })
+            .once();
+
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            "Would create debug.json",
+            "Unchanged keymap.json",
+            "Would update tasks.json",
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut file_pusher = FilePusher::new(
+            &mock_client,
+            &mut mock_io,
+            FileFilter::default(),
+            true,
+            OutputFormat::Text,
+        );
+        file_pusher.push_files().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use common::{
+    interactive_io::InteractiveIO,
+    sync::{
+        CURRENT_OS, Client, FileFilter, SyncStateStore, SyncStatus, materialize_os_sections,
+        sync_status,
+    },
+};
+#[cfg(not(test))]
+use paths as zed_paths;
+use serde::Serialize;
+#[cfg(test)]
+use test_support::zed_paths;
+use zed_extension_api::serde_json::to_string_pretty;
+
+use crate::local_files::collect_syncable_files;
+
+#[derive(Debug, Serialize)]
+struct StatusReport {
+    remote_revision: Option<String>,
+    files: Vec<FileStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileStatus {
+    file_name: String,
+    status: SyncStatus,
+    last_synced_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub struct FileStatusReporter<'a> {
+    client: &'a dyn Client,
+    io: &'a mut dyn InteractiveIO,
+    file_filter: FileFilter,
+    json: bool,
+}
+
+impl<'a> FileStatusReporter<'a> {
+    pub fn new(
+        client: &'a dyn Client,
+        io: &'a mut dyn InteractiveIO,
+        file_filter: FileFilter,
+        json: bool,
+    ) -> Self {
+        FileStatusReporter {
+            client,
+            io,
+            file_filter,
+            json,
+        }
+    }
+
+    pub async fn report_status(&mut self) -> Result<()> {
+        let remote_revision = self.client.remote_revision().await?;
+
+        let mut remote_files = BTreeMap::new();
+        let mut errors = BTreeMap::new();
+        for file_load_result in self.client.load_files().await? {
+            match file_load_result {
+                Ok((file_name, content)) => {
+                    // comparing with the remote file the way it would be written by `load`
+                    remote_files.insert(file_name, materialize_os_sections(&content, CURRENT_OS)?);
+                }
+                Err(e) => {
+                    errors.insert(e.file_name().to_string(), e.to_string());
+                }
+            }
+        }
+
+        let local_files: BTreeMap<_, _> = collect_syncable_files(&self.file_filter)?
+            .into_iter()
+            .map(|data| (data.filename, data.body))
+            .collect();
+        let sync_state = SyncStateStore::new(&zed_paths::data_dir()).load()?;
+
+        let file_names: BTreeSet<_> = local_files
+            .keys()
+            .chain(remote_files.keys())
+            .chain(errors.keys())
+            .collect();
+        let files = file_names
+            .into_iter()
+            .map(|file_name| {
+                let file_state = sync_state.file(file_name);
+                let error = errors.get(file_name).cloned();
+                let status = if error.is_some() {
+                    SyncStatus::Error
+                } else {
+                    sync_status(
+                        local_files.get(file_name),
+                        remote_files.get(file_name),
+                        file_state,
+                    )
+                };
+
+                FileStatus {
+                    file_name: file_name.clone(),
+                    status,
+                    last_synced_at: file_state.map(|state| state.synced_at),
+                    error,
+                }
+            })
+            .collect();
+
+        let report = StatusReport {
+            remote_revision,
+            files,
+        };
+
+        if self.json {
+            self.io.write_line(&to_string_pretty(&report)?)?;
+        } else {
+            self.write_table(&report)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_table(&mut self, report: &StatusReport) -> Result<()> {
+        self.io.write_line(&format!(
+            "Remote revision: {}",
+            report.remote_revision.as_deref().unwrap_or("unknown")
+        ))?;
+
+        let name_width = report
+            .files
+            .iter()
+            .map(|file| file.file_name.len())
+            .chain(["FILE".len()])
+            .max()
+            .unwrap_or_default();
+        let status_width = SyncStatus::RemotelyModified.to_string().len();
+
+        self.io.write_line(&format!(
+            "{:<name_width$}  {:<status_width$}  LAST SYNCED",
+            "FILE", "STATUS"
+        ))?;
+        for file in &report.files {
+            let last_synced_at = file.last_synced_at.map_or_else(
+                || "never".to_string(),
+                |time| time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            );
+            self.io.write_line(&format!(
+                "{:<name_width$}  {:<status_width$}  {last_synced_at}",
+                file.file_name, file.status
+            ))?;
+        }
+
+        for error in report.files.iter().filter_map(|file| file.error.as_ref()) {
+            self.io.write_line(&format!("🔴 {error}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub FileStatusReporter {
+        pub fn new<'a>(client: &'a dyn Client, io: &'a mut dyn InteractiveIO, file_filter: FileFilter, json: bool) -> Self;
+        pub async fn report_status(&mut self) -> Result<()>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use common::{interactive_io::MockInteractiveIO, sync::MockGithubClient};
+    use mockall::{Sequence, predicate};
+    use test_support::zed_config_dir;
+    use zed_extension_api::serde_json::{Value, from_str, json};
+
+    use super::*;
+
+    fn mock_client() -> MockGithubClient {
+        let mut mock_client = MockGithubClient::default();
+        mock_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("4c7f3a".to_string())));
+        mock_client.expect_load_files().returning(|| {
+            Ok(Box::new(
+                [
+                    Ok(("tasks.json".to_string(), "[]".to_string())),
+                    Ok((
+                        "keymap.json".to_string(),
+                        r#"[{ "bindings": { "cmd-k": "workspace::Save" } }]"#.to_string(),
+                    )),
+                    Ok((
+                        "settings.json".to_string(),
+                        r#"{ "theme": "Ayu Dark" }"#.to_string(),
+                    )),
+                ]
+                .into_iter(),
+            ))
+        });
+
+        mock_client
+    }
+
+    #[tokio::test]
+    async fn test_status_table() -> Result<()> {
+        zed_config_dir().child("tasks.json").write_str("[\n]")?;
+        zed_config_dir().child("debug.json").write_str("[]")?;
+        zed_config_dir()
+            .child("settings.json")
+            .write_str(r#"{ "theme": "One Dark" }"#)?;
+
+        let mock_client = mock_client();
+
+        let mut seq = Sequence::new();
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            "Remote revision: 4c7f3a",
+            "FILE           STATUS             LAST SYNCED",
+            "debug.json     missing remotely   never",
+            "keymap.json    missing locally    never",
+            "settings.json  diverged           never",
+            "tasks.json     in sync            never",
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut reporter =
+            FileStatusReporter::new(&mock_client, &mut mock_io, FileFilter::default(), false);
+        reporter.report_status().await
+    }
+
+    #[tokio::test]
+    async fn test_modified_side_is_detected_from_sync_state() -> Result<()> {
+        let state_store = SyncStateStore::new(&zed_paths::data_dir());
+
+        zed_config_dir()
+            .child("settings.json")
+            .write_str(r#"{ "theme": "One Dark" }"#)?;
+        state_store.record_synced("settings.json", r#"{ "theme": "Ayu Dark" }"#)?;
+
+        zed_config_dir()
+            .child("keymap.json")
+            .write_str(r#"[{ "bindings": { "cmd-k": "workspace::SaveAll" } }]"#)?;
+        state_store.record_synced(
+            "keymap.json",
+            r#"[{ "bindings": { "cmd-k": "workspace::SaveAll" } }]"#,
+        )?;
+
+        let mock_client = mock_client();
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .withf(|output| {
+                from_str::<Value>(output).is_ok_and(|report| {
+                    let statuses: Vec<_> = report["files"]
+                        .as_array()
+                        .into_iter()
+                        .flatten()
+                        .map(|file| (file["file_name"].clone(), file["status"].clone()))
+                        .collect();
+
+                    report["remote_revision"] == "4c7f3a"
+                        && statuses
+                            == vec![
+                                (json!("keymap.json"), json!("remotely_modified")),
+                                (json!("settings.json"), json!("locally_modified")),
+                                (json!("tasks.json"), json!("missing_locally")),
+                            ]
+                        && report["files"][0]["last_synced_at"].is_string()
+                })
+            })
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut reporter =
+            FileStatusReporter::new(&mock_client, &mut mock_io, FileFilter::default(), true);
+        reporter.report_status().await
+    }
+}
@@ -0,0 +1,32 @@
+use std::{ffi::OsStr, fs, path::Path};
+
+use anyhow::Result;
+use common::sync::{FileFilter, LocalFileData};
+#[cfg(not(test))]
+use paths as zed_paths;
+#[cfg(test)]
+use test_support::zed_paths;
+
+/// Reads the config files from the Zed config dir that are subject to sync, sorted by file name
+pub fn collect_syncable_files(file_filter: &FileFilter) -> Result<Vec<LocalFileData>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(zed_paths::config_dir())? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(OsStr::to_str) else {
+            continue;
+        };
+
+        if path.is_file()
+            && file_name.to_lowercase().ends_with(".json")
+            && file_filter.matches(Path::new(file_name))
+        {
+            let body = fs::read_to_string(&path)?;
+            files.push(LocalFileData::new(path, body)?);
+        }
+    }
+
+    files.sort_by(|a, b| a.filename.cmp(&b.filename));
+
+    Ok(files)
+}
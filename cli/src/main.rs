@@ -1,19 +1,58 @@
-use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
 #[double]
 use common::config::Config;
+use common::config::ConfigOverrides;
 use common::interactive_io::InteractiveIO;
 #[double]
 use common::sync::GithubClient;
+use common::sync::{
+    ArchiveClient, BackupStore, Client, DEFAULT_BACKUP_RETENTION, SyncEventFilter, SyncEventLog,
+    SyncOptions, SyncResult, export_archive, has_os_sections, short_id,
+};
 use mockall_double::double;
+#[cfg(not(test))]
+use paths as zed_paths;
 #[cfg(test)]
 use test_support::nextest_only;
+#[cfg(test)]
+use test_support::zed_paths;
+use zed_extension_api::serde_json::{json, to_string};
 
+#[double]
+use crate::doctor::Doctor;
+#[double]
+use crate::file_differ::FileDiffer;
 #[double]
 use crate::file_loader::FileLoader;
+use crate::file_loader::FileSelection;
+#[double]
+use crate::file_pusher::FilePusher;
+#[double]
+use crate::file_status::FileStatusReporter;
+#[double]
+use crate::migrator::Migrator;
+use crate::migrator::Provider;
+use crate::output::{EventReporter, FileEvent, OutputFormat, Summary, write_summary};
 use crate::std_interactive_io::StdInteractiveIO;
 
+mod doctor;
+mod file_differ;
 mod file_loader;
+mod file_pusher;
+mod file_status;
+mod local_files;
+mod migrator;
+mod output;
+mod settings_file;
 mod std_interactive_io;
 
 #[derive(Debug, Parser)]
@@ -21,64 +60,565 @@ mod std_interactive_io;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+    /// Number of backups of overwritten local files to keep, 0 keeps all of them
+    #[arg(long, global = true, default_value_t = DEFAULT_BACKUP_RETENTION)]
+    pub keep_backups: usize,
+    /// ID of the gist to sync with, overrides the one from the environment and the Zed settings file
+    #[arg(long, global = true)]
+    pub gist_id: Option<String>,
+    /// Name of the environment variable holding the Github token
+    #[arg(long, global = true)]
+    pub token_env: Option<String>,
+    /// Output format, `json` prints one JSON object per line
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+}
+
+// exit code of a run that completed, but failed to process some of the files
+const PARTIAL_FAILURE_EXIT_CODE: u8 = 2;
+
+impl Cli {
+    fn config_source(&self) -> ConfigSource {
+        ConfigSource {
+            overrides: ConfigOverrides {
+                gist_id: self.gist_id.clone(),
+                token_env: self.token_env.clone(),
+            },
+            // prompting for missing values would hang a script or a CI job
+            interactive: std::io::stdin().is_terminal(),
+        }
+    }
+}
+
+/// Where the commands syncing with the gist get their configuration from
+#[derive(Debug, Clone, Default)]
+struct ConfigSource {
+    overrides: ConfigOverrides,
+    interactive: bool,
+}
+
+impl ConfigSource {
+    fn resolve<T: InteractiveIO + 'static>(&self, io: &mut T) -> Result<Config> {
+        Config::resolve(io, &self.overrides, self.interactive)
+    }
 }
 
 #[derive(Debug, Subcommand)]
 pub enum Command {
-    /// Load all Zed user settings files from a gist
+    /// Load Zed user settings files from a gist
     Load {
+        /// Names of the files to load, all files if none are given
+        files: Vec<String>,
+        /// Glob patterns of the files to skip
+        #[arg(long)]
+        exclude: Vec<String>,
+        /// Force overwriting local settings files even if they exist
+        #[arg(short, long, default_value_t = false)]
+        force: bool,
+        /// Load the files from an archive created by `export` instead of the gist
+        #[arg(long)]
+        from_archive: Option<PathBuf>,
+    },
+    /// Write Zed user settings files into a tar.gz archive, to be imported on another machine
+    Export {
+        /// Path of the archive to create
+        archive: PathBuf,
+    },
+    /// Load Zed user settings files from an archive created by `export`
+    Import {
+        /// Path of the archive
+        archive: PathBuf,
         /// Force overwriting local settings files even if they exist
         #[arg(short, long, default_value_t = false)]
         force: bool,
-        // TODO: add an option to create a new gist on the fly,
-        // copy settings to it and start using it from now on
+    },
+    /// Create a new gist, copy Zed user settings files to it and configure the extension to use it
+    Init,
+    /// Push local Zed user settings files to a gist
+    Push {
+        /// List the files that would be created or updated without pushing them
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// Show what loading settings files from a gist would change locally
+    Diff {
+        /// Print the changes as JSON, same as `--output json`
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Copy the stored settings files from one provider to another, e.g. `--from gist:<id> --to gist:<id>`
+    Migrate {
+        /// Provider to copy the files from, `gist:<id>` or `archive:<path>`
+        #[arg(long)]
+        from: Provider,
+        /// Provider to copy the files to, `gist:<id>`
+        #[arg(long)]
+        to: Provider,
+        /// Replay the revision history of the source provider, oldest first
+        #[arg(long, default_value_t = false)]
+        history: bool,
+    },
+    /// List backups of local files overwritten by this tool, or restore one of them
+    RestoreBackup {
+        /// Name of the backup to restore, lists the backups if not given
+        backup: Option<String>,
+    },
+    /// Check the extension setup and suggest fixes for the problems found
+    Doctor,
+    /// Show the sync state of each Zed user settings file
+    Status {
+        /// Print the state as JSON, same as `--output json`
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Show the logged sync events, oldest first
+    Log {
+        /// Show only the events of this file
+        #[arg(long)]
+        file: Option<String>,
+        /// Show only the events at or after this time, e.g. `2026-01-02T03:04:05Z`
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+        /// Show only the events at or before this time
+        #[arg(long)]
+        until: Option<DateTime<Utc>>,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let args = Cli::parse();
+    let config_source = args.config_source();
+    let output = args.output;
     let mut std_io = StdInteractiveIO;
 
-    match args.command {
-        Command::Load { force } => {
-            load(&mut std_io, force).await?;
+    let summary = match args.command {
+        Command::Load {
+            files,
+            exclude,
+            force,
+            from_archive,
+        } => {
+            let selection = FileSelection::new(files, &exclude)?;
+            let backups = BackupStore::new(&zed_paths::data_dir(), args.keep_backups);
+
+            match from_archive {
+                Some(archive) => {
+                    load_from_archive(&mut std_io, &archive, force, selection, backups, output)
+                        .await?
+                }
+                None => {
+                    load(
+                        &mut std_io,
+                        &config_source,
+                        force,
+                        selection,
+                        backups,
+                        output,
+                    )
+                    .await?
+                }
+            }
+        }
+        Command::Export { archive } => {
+            export(&mut std_io, &config_source, &archive, output).await?
+        }
+        Command::Import { archive, force } => {
+            load_from_archive(
+                &mut std_io,
+                &archive,
+                force,
+                FileSelection::default(),
+                BackupStore::new(&zed_paths::data_dir(), args.keep_backups),
+                output,
+            )
+            .await?
+        }
+        Command::Init => {
+            init(
+                &mut std_io,
+                BackupStore::new(&zed_paths::data_dir(), args.keep_backups),
+                output,
+            )
+            .await?
+        }
+        Command::Push { dry_run } => push(&mut std_io, &config_source, dry_run, output).await?,
+        Command::Diff { json } => {
+            let json = json || output == OutputFormat::Json;
+            diff(&mut std_io, &config_source, json).await?;
+
+            // keeping the output a single JSON document
+            if json {
+                return Ok(ExitCode::SUCCESS);
+            }
+            Summary::default()
+        }
+        Command::Migrate { from, to, history } => {
+            migrate(&mut std_io, &config_source, from, to, history, output).await?
+        }
+        Command::RestoreBackup { backup } => restore_backup(
+            &mut std_io,
+            backup,
+            BackupStore::new(&zed_paths::data_dir(), args.keep_backups),
+            output,
+        )?,
+        Command::Doctor => doctor(&mut std_io, output).await?,
+        Command::Status { json } => {
+            let json = json || output == OutputFormat::Json;
+            status(&mut std_io, &config_source, json).await?;
+
+            if json {
+                return Ok(ExitCode::SUCCESS);
+            }
+            Summary::default()
         }
+        Command::Log { file, since, until } => {
+            show_log(
+                &mut std_io,
+                &SyncEventLog::new(&zed_paths::data_dir()),
+                &SyncEventFilter { file, since, until },
+                output,
+            )?;
+
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+
+    write_summary(&mut std_io, output, summary)?;
+
+    if summary.failed > 0 {
+        Ok(ExitCode::from(PARTIAL_FAILURE_EXIT_CODE))
+    } else {
+        Ok(ExitCode::SUCCESS)
     }
+}
 
-    std_io.write_line("🟢 All done.")?;
+async fn load<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    force: bool,
+    selection: FileSelection,
+    backups: BackupStore,
+    output: OutputFormat,
+) -> Result<Summary> {
+    let config = config_source.resolve(io)?;
+    let sync_options = config.sync_options()?;
+    let selection = selection.with_synced_files(sync_options.file_filter.clone());
 
-    Ok(())
+    let client = GithubClient::new(
+        config.gist_id().into(),
+        config.github_token().into(),
+        sync_options,
+    )?;
+    let mut loader = FileLoader::new(&client, io, force, selection, backups, output);
+
+    loader.load_files().await
 }
 
-async fn load<T: InteractiveIO + 'static>(io: &mut T, force: bool) -> Result<()> {
-    let config = Config::from_interactive_io(io)?;
+async fn load_from_archive<T: InteractiveIO + 'static>(
+    io: &mut T,
+    archive: &Path,
+    force: bool,
+    selection: FileSelection,
+    backups: BackupStore,
+    output: OutputFormat,
+) -> Result<Summary> {
+    // the archive works without Github access, the local configuration only fills in the masked token
+    let (github_token, sync_options) = local_sync_config()?;
+    let selection = selection.with_synced_files(sync_options.file_filter.clone());
 
-    let client = GithubClient::new(config.gist_id().into(), config.github_token().into())?;
-    let mut loader = FileLoader::new(&client, io, force);
+    let client = ArchiveClient::open(archive, github_token, sync_options)?;
+    let mut loader = FileLoader::new(&client, io, force, selection, backups, output);
 
     loader.load_files().await
 }
 
+async fn export<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    archive: &Path,
+    output: OutputFormat,
+) -> Result<Summary> {
+    let (_, sync_options) = local_sync_config()?;
+    let files = local_files::collect_syncable_files(&sync_options.file_filter)?;
+
+    // sections for other OSes are emptied in the local files, only the gist has their contents
+    let stored_files = if files.iter().any(|data| has_os_sections(&data.body)) {
+        let config = config_source.resolve(io)?;
+        let client = GithubClient::new(
+            config.gist_id().into(),
+            config.github_token().into(),
+            sync_options.clone(),
+        )?;
+        client
+            .load_raw_files(None)
+            .await
+            .context("Failed to load the sections for other OSes from the gist")?
+    } else {
+        BTreeMap::new()
+    };
+
+    let manifest = export_archive(
+        archive,
+        &files,
+        &stored_files,
+        &sync_options,
+        env!("CARGO_PKG_VERSION"),
+    )?;
+
+    let mut reporter = EventReporter::new(output);
+    for file_name in manifest.files.into_keys() {
+        reporter.report(io, &FileEvent::Exported { file_name })?;
+    }
+
+    Ok(reporter.summary())
+}
+
+// the token and the sync options from the settings file, if the extension is configured there
+fn local_sync_config() -> Result<(Option<String>, SyncOptions)> {
+    match Config::from_settings_file() {
+        Ok(config) => Ok((
+            Some(config.github_token().to_string()),
+            config.sync_options()?,
+        )),
+        Err(_) => Ok((None, SyncOptions::default())),
+    }
+}
+
+async fn migrate<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    from: Provider,
+    to: Provider,
+    history: bool,
+    output: OutputFormat,
+) -> Result<Summary> {
+    if from == to {
+        bail!("Source and target providers are the same: {from}");
+    }
+    let Provider::Gist(target_gist_id) = to else {
+        bail!("Archives can't be migrated to, use `export` to create one");
+    };
+
+    // the token and the sync options come from the usual configuration, the gists from the providers
+    let mut config_source = config_source.clone();
+    config_source
+        .overrides
+        .gist_id
+        .get_or_insert_with(|| target_gist_id.clone());
+    let config = config_source.resolve(io)?;
+    let github_token = config.github_token().to_string();
+    let sync_options = config.sync_options()?;
+
+    let source: Box<dyn Client> = match from {
+        Provider::Gist(gist_id) => Box::new(GithubClient::new(
+            gist_id,
+            github_token.clone(),
+            sync_options.clone(),
+        )?),
+        Provider::Archive(path) => Box::new(ArchiveClient::open(
+            &path,
+            Some(github_token.clone()),
+            sync_options.clone(),
+        )?),
+    };
+    let target = GithubClient::new(target_gist_id, github_token, sync_options)?;
+    let mut migrator = Migrator::new(source.as_ref(), &target, io, history, output);
+
+    migrator.migrate().await
+}
+
+fn restore_backup<T: InteractiveIO>(
+    io: &mut T,
+    backup: Option<String>,
+    mut backups: BackupStore,
+    output: OutputFormat,
+) -> Result<Summary> {
+    let Some(backup) = backup else {
+        let snapshots = backups.snapshots()?;
+        if snapshots.is_empty() && output == OutputFormat::Text {
+            io.write_line("No backups found")?;
+        }
+        for snapshot in snapshots {
+            match output {
+                OutputFormat::Text => io.write_line(&format!(
+                    "{}: {}",
+                    snapshot.name,
+                    snapshot.file_names.join(", ")
+                ))?,
+                OutputFormat::Json => io.write_line(&to_string(&snapshot)?)?,
+            }
+        }
+
+        return Ok(Summary::default());
+    };
+
+    let mut reporter = EventReporter::new(output);
+    for file_name in backups.restore(&backup, &zed_paths::config_dir())? {
+        reporter.report(io, &FileEvent::Restored { file_name })?;
+    }
+
+    Ok(reporter.summary())
+}
+
+async fn doctor<T: InteractiveIO>(io: &mut T, output: OutputFormat) -> Result<Summary> {
+    let mut doctor = Doctor::new(io, output);
+
+    doctor.run_checks().await
+}
+
+async fn init<T: InteractiveIO + 'static>(
+    io: &mut T,
+    mut backups: BackupStore,
+    output: OutputFormat,
+) -> Result<Summary> {
+    if settings_file::has_initialization_options()? {
+        bail!("Settings sync is already configured in your Zed settings file");
+    }
+
+    let github_token = Config::read_github_token(io)?;
+    let gist_id = GithubClient::create_gist(github_token.clone()).await?;
+    settings_file::write_initialization_options(&gist_id, &github_token, &mut backups)?;
+    match output {
+        OutputFormat::Text => io.write_line(&format!(
+            "Created gist {gist_id} and added it to your Zed settings file"
+        ))?,
+        OutputFormat::Json => io.write_line(&to_string(
+            &json!({ "event": "gist_created", "gist_id": gist_id }),
+        )?)?,
+    }
+
+    // pushing through the regular sync path, so the token in settings.json gets masked
+    let config = Config::from_settings_file()?;
+    let sync_options = config.sync_options()?;
+    let client = GithubClient::new(gist_id, github_token, sync_options.clone())?;
+    let mut pusher = FilePusher::new(&client, io, sync_options.file_filter, false, output);
+
+    pusher.push_files().await
+}
+
+async fn push<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    dry_run: bool,
+    output: OutputFormat,
+) -> Result<Summary> {
+    let config = config_source.resolve(io)?;
+    let sync_options = config.sync_options()?;
+
+    let client = GithubClient::new(
+        config.gist_id().into(),
+        config.github_token().into(),
+        sync_options.clone(),
+    )?;
+    let mut pusher = FilePusher::new(&client, io, sync_options.file_filter, dry_run, output);
+
+    pusher.push_files().await
+}
+
+async fn diff<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    json: bool,
+) -> Result<()> {
+    let config = config_source.resolve(io)?;
+
+    let client = GithubClient::new(
+        config.gist_id().into(),
+        config.github_token().into(),
+        config.sync_options()?,
+    )?;
+    let mut differ = FileDiffer::new(&client, io, json);
+
+    differ.diff_files().await
+}
+
+async fn status<T: InteractiveIO + 'static>(
+    io: &mut T,
+    config_source: &ConfigSource,
+    json: bool,
+) -> Result<()> {
+    let config = config_source.resolve(io)?;
+    let sync_options = config.sync_options()?;
+
+    let client = GithubClient::new(
+        config.gist_id().into(),
+        config.github_token().into(),
+        sync_options.clone(),
+    )?;
+    let mut reporter = FileStatusReporter::new(&client, io, sync_options.file_filter, json);
+
+    reporter.report_status().await
+}
+
+fn show_log<T: InteractiveIO>(
+    io: &mut T,
+    log: &SyncEventLog,
+    filter: &SyncEventFilter,
+    output: OutputFormat,
+) -> Result<()> {
+    let events = log.query(filter)?;
+    if events.is_empty() && output == OutputFormat::Text {
+        io.write_line("No sync events found")?;
+    }
+
+    for event in events {
+        match output {
+            OutputFormat::Text => {
+                let result = match event.result {
+                    SyncResult::Ok => "ok".to_string(),
+                    SyncResult::Error => {
+                        format!(
+                            "error ({})",
+                            event.error_kind.as_deref().unwrap_or("unknown")
+                        )
+                    }
+                };
+                io.write_line(&format!(
+                    "{} {:<4} {} {} bytes, hash {}, rev {}: {result}",
+                    event.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                    event.direction,
+                    event.file,
+                    event.size,
+                    short_id(&event.hash),
+                    event.remote_revision.as_deref().map_or("-", short_id),
+                ))?;
+            }
+            OutputFormat::Json => io.write_line(&to_string(&event)?)?,
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 nextest_only!();
 
 #[cfg(test)]
 mod tests {
-    use anyhow::Result;
+    use anyhow::{Result, anyhow};
+    use assert_fs::prelude::*;
     use common::{
         config::MockConfig,
         interactive_io::MockInteractiveIO,
         sync::{
             __mock_MockGithubClient::__new::Context as MockGithubClientNewContext, Client,
-            MockGithubClient,
+            MockGithubClient, SyncDirection, SyncEvent, SyncOptions,
         },
     };
     use mockall::{Sequence, predicate};
+    use test_support::zed_config_dir;
 
     use super::*;
-    use crate::file_loader::{
-        __mock_MockFileLoader::__new::Context as MockFileLoaderNewContext, MockFileLoader,
+    use crate::{
+        doctor::MockDoctor,
+        file_differ::MockFileDiffer,
+        file_loader::{
+            __mock_MockFileLoader::__new::Context as MockFileLoaderNewContext, MockFileLoader,
+        },
+        file_pusher::MockFilePusher,
+        file_status::MockFileStatusReporter,
+        migrator::MockMigrator,
     };
 
     fn setup_interactive_io_mock(io: &mut MockInteractiveIO, seq: &mut Sequence) {
@@ -100,7 +640,7 @@ mod tests {
         github_token: Option<String>,
     ) {
         gh_client_ctx.expect().in_sequence(seq).returning(
-            move |gist_id_received, github_token_received| {
+            move |gist_id_received, github_token_received, _options| {
                 if let Some(ref gist_id_value) = gist_id {
                     assert_eq!(gist_id_value, &gist_id_received);
                 }
@@ -116,10 +656,8 @@ mod tests {
             },
         );
 
-        file_loader_ctx
-            .expect()
-            .in_sequence(seq)
-            .returning(move |client, _io, force_received| {
+        file_loader_ctx.expect().in_sequence(seq).returning(
+            move |client, _io, force_received, _selection, _backups, _output| {
                 // testing that FileLoader has received the correct client, configured from Config properties
                 // TODO: remove this allow once clippy is fixed
                 #[allow(clippy::ref_as_ptr, clippy::ptr_as_ptr, clippy::cast_ptr_alignment)]
@@ -130,9 +668,12 @@ mod tests {
                 assert_eq!(force, force_received);
 
                 let mut mock_file_loader = MockFileLoader::default();
-                mock_file_loader.expect_load_files().returning(|| Ok(()));
                 mock_file_loader
-            });
+                    .expect_load_files()
+                    .returning(|| Ok(Summary::default()));
+                mock_file_loader
+            },
+        );
     }
 
     #[tokio::test]
@@ -142,24 +683,37 @@ mod tests {
         let mut io = MockInteractiveIO::default();
         setup_interactive_io_mock(&mut io, &mut seq);
 
-        let ctx = MockConfig::from_interactive_io_context();
-        ctx.expect()
-            .in_sequence(&mut seq)
-            .returning(|_io: &mut MockInteractiveIO| {
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
                 let mut mock_config = MockConfig::default();
                 mock_config.expect_gist_id().return_const(String::default());
                 mock_config
                     .expect_github_token()
                     .return_const(String::default());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
                 Ok(mock_config)
-            });
+            },
+        );
 
         // we need to create contexts in the test function so they are not dropped before the test finishes
         let gh_ctx = MockGithubClient::new_context();
         let file_loader_ctx = MockFileLoader::new_context();
         setup_client_and_loader_mocks(&mut seq, &gh_ctx, &file_loader_ctx, false, None, None);
 
-        load(&mut io, false).await
+        load(
+            &mut io,
+            &ConfigSource::default(),
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        )
+        .await?;
+
+        Ok(())
     }
 
     #[tokio::test]
@@ -169,24 +723,37 @@ mod tests {
         let mut io = MockInteractiveIO::default();
         setup_interactive_io_mock(&mut io, &mut seq);
 
-        let ctx = MockConfig::from_interactive_io_context();
-        ctx.expect()
-            .in_sequence(&mut seq)
-            .returning(|_io: &mut MockInteractiveIO| {
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
                 let mut mock_config = MockConfig::default();
                 mock_config.expect_gist_id().return_const(String::default());
                 mock_config
                     .expect_github_token()
                     .return_const(String::default());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
                 Ok(mock_config)
-            });
+            },
+        );
 
         // we need to create contexts in the test function so they are not dropped before the test finishes
         let gh_ctx = MockGithubClient::new_context();
         let file_loader_ctx = MockFileLoader::new_context();
         setup_client_and_loader_mocks(&mut seq, &gh_ctx, &file_loader_ctx, true, None, None);
 
-        load(&mut io, true).await
+        load(
+            &mut io,
+            &ConfigSource::default(),
+            true,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        )
+        .await?;
+
+        Ok(())
     }
 
     #[tokio::test]
@@ -199,10 +766,9 @@ mod tests {
         let mut io = MockInteractiveIO::default();
         setup_interactive_io_mock(&mut io, &mut seq);
 
-        let ctx = MockConfig::from_interactive_io_context();
-        ctx.expect()
-            .in_sequence(&mut seq)
-            .returning(|_io: &mut MockInteractiveIO| {
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
                 let mut mock_config = MockConfig::default();
                 mock_config
                     .expect_gist_id()
@@ -210,8 +776,12 @@ mod tests {
                 mock_config
                     .expect_github_token()
                     .return_const(github_token.to_string());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
                 Ok(mock_config)
-            });
+            },
+        );
 
         // we need to create contexts in the test function so they are not dropped before the test finishes
         let gh_ctx = MockGithubClient::new_context();
@@ -225,6 +795,440 @@ mod tests {
             Some(github_token.to_string()),
         );
 
-        load(&mut io, false).await
+        load(
+            &mut io,
+            &ConfigSource::default(),
+            false,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_is_passed_to_file_pusher() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut io = MockInteractiveIO::default();
+
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
+                let mut mock_config = MockConfig::default();
+                mock_config.expect_gist_id().return_const(String::default());
+                mock_config
+                    .expect_github_token()
+                    .return_const(String::default());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
+                Ok(mock_config)
+            },
+        );
+
+        // we need to create contexts in the test function so they are not dropped before the test finishes
+        let gh_ctx = MockGithubClient::new_context();
+        gh_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .returning(|_gist_id, _github_token, _options| Ok(MockGithubClient::default()));
+
+        let file_pusher_ctx = MockFilePusher::new_context();
+        file_pusher_ctx.expect().in_sequence(&mut seq).returning(
+            |_client, _io, _file_filter, dry_run_received, _output| {
+                assert!(dry_run_received);
+
+                let mut mock_file_pusher = MockFilePusher::default();
+                mock_file_pusher
+                    .expect_push_files()
+                    .returning(|| Ok(Summary::default()));
+                mock_file_pusher
+            },
+        );
+
+        push(&mut io, &ConfigSource::default(), true, OutputFormat::Text).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_is_passed_to_file_differ() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut io = MockInteractiveIO::default();
+
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
+                let mut mock_config = MockConfig::default();
+                mock_config.expect_gist_id().return_const(String::default());
+                mock_config
+                    .expect_github_token()
+                    .return_const(String::default());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
+                Ok(mock_config)
+            },
+        );
+
+        // we need to create contexts in the test function so they are not dropped before the test finishes
+        let gh_ctx = MockGithubClient::new_context();
+        gh_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .returning(|_gist_id, _github_token, _options| Ok(MockGithubClient::default()));
+
+        let file_differ_ctx = MockFileDiffer::new_context();
+        file_differ_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .returning(|_client, _io, json_received| {
+                assert!(json_received);
+
+                let mut mock_file_differ = MockFileDiffer::default();
+                mock_file_differ.expect_diff_files().returning(|| Ok(()));
+                mock_file_differ
+            });
+
+        diff(&mut io, &ConfigSource::default(), true).await
+    }
+
+    #[tokio::test]
+    async fn test_json_is_passed_to_file_status_reporter() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut io = MockInteractiveIO::default();
+
+        let ctx = MockConfig::resolve_context();
+        ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, _overrides, _interactive| {
+                let mut mock_config = MockConfig::default();
+                mock_config.expect_gist_id().return_const(String::default());
+                mock_config
+                    .expect_github_token()
+                    .return_const(String::default());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
+                Ok(mock_config)
+            },
+        );
+
+        // we need to create contexts in the test function so they are not dropped before the test finishes
+        let gh_ctx = MockGithubClient::new_context();
+        gh_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .returning(|_gist_id, _github_token, _options| Ok(MockGithubClient::default()));
+
+        let reporter_ctx = MockFileStatusReporter::new_context();
+        reporter_ctx.expect().in_sequence(&mut seq).returning(
+            |_client, _io, _file_filter, json_received| {
+                assert!(json_received);
+
+                let mut mock_reporter = MockFileStatusReporter::default();
+                mock_reporter.expect_report_status().returning(|| Ok(()));
+                mock_reporter
+            },
+        );
+
+        status(&mut io, &ConfigSource::default(), true).await
+    }
+
+    #[tokio::test]
+    async fn test_init_creates_gist_and_pushes_files() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut io = MockInteractiveIO::default();
+
+        let token_ctx = MockConfig::read_github_token_context();
+        token_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .returning(|_io: &mut MockInteractiveIO| Ok("gho_token".to_string()));
+
+        let create_gist_ctx = MockGithubClient::create_gist_context();
+        create_gist_ctx
+            .expect()
+            .in_sequence(&mut seq)
+            .with(predicate::eq("gho_token".to_string()))
+            .returning(|_| Ok("new_gist_id".to_string()));
+
+        io.expect_write_line()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(
+                "Created gist new_gist_id and added it to your Zed settings file",
+            ))
+            .returning(|_| Ok(()));
+
+        let config_ctx = MockConfig::from_settings_file_context();
+        config_ctx.expect().in_sequence(&mut seq).returning(|| {
+            let mut mock_config = MockConfig::default();
+            mock_config
+                .expect_sync_options()
+                .returning(|| Ok(SyncOptions::default()));
+            Ok(mock_config)
+        });
+
+        let gh_ctx = MockGithubClient::new_context();
+        gh_ctx.expect().in_sequence(&mut seq).returning(
+            |gist_id_received, github_token_received, _options| {
+                assert_eq!(gist_id_received, "new_gist_id");
+                assert_eq!(github_token_received, "gho_token");
+                Ok(MockGithubClient::default())
+            },
+        );
+
+        let file_pusher_ctx = MockFilePusher::new_context();
+        file_pusher_ctx.expect().in_sequence(&mut seq).returning(
+            |_client, _io, _file_filter, dry_run_received, _output| {
+                assert!(!dry_run_received);
+
+                let mut mock_file_pusher = MockFilePusher::default();
+                mock_file_pusher
+                    .expect_push_files()
+                    .returning(|| Ok(Summary::default()));
+                mock_file_pusher
+            },
+        );
+
+        init(
+            &mut io,
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        )
+        .await?;
+
+        assert!(settings_file::has_initialization_options()?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exported_archive_is_imported() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+        let archive = zed_paths::data_dir().join("settings.tar.gz");
+
+        let config_ctx = MockConfig::from_settings_file_context();
+        config_ctx
+            .expect()
+            .returning(|| Err(anyhow!("Settings file not found")));
+
+        let mut io = MockInteractiveIO::default();
+        io.expect_write_line()
+            .with(predicate::eq("Exported keymap.json"))
+            .returning(|_| Ok(()))
+            .once();
+
+        assert_eq!(
+            export(
+                &mut io,
+                &ConfigSource::default(),
+                &archive,
+                OutputFormat::Text
+            )
+            .await?
+            .succeeded,
+            1
+        );
+
+        let file_loader_ctx = MockFileLoader::new_context();
+        file_loader_ctx.expect().returning(
+            |_client, _io, force_received, _selection, _backups, _output| {
+                assert!(force_received);
+
+                let mut mock_file_loader = MockFileLoader::default();
+                mock_file_loader
+                    .expect_load_files()
+                    .returning(|| Ok(Summary::default()));
+                mock_file_loader
+            },
+        );
+
+        load_from_archive(
+            &mut io,
+            &archive,
+            true,
+            FileSelection::default(),
+            BackupStore::new(&zed_paths::data_dir(), 0),
+            OutputFormat::Text,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_providers_are_passed_to_migrator() -> Result<()> {
+        let mut seq = Sequence::new();
+        let mut io = MockInteractiveIO::default();
+
+        let config_ctx = MockConfig::resolve_context();
+        config_ctx.expect().in_sequence(&mut seq).returning(
+            |_io: &mut MockInteractiveIO, overrides: &ConfigOverrides, _interactive| {
+                // the target gist fills in a gist ID missing from the configuration
+                assert_eq!(overrides.gist_id.as_deref(), Some("target"));
+
+                let mut mock_config = MockConfig::default();
+                mock_config
+                    .expect_github_token()
+                    .return_const("abcdefg".to_string());
+                mock_config
+                    .expect_sync_options()
+                    .returning(|| Ok(SyncOptions::default()));
+                Ok(mock_config)
+            },
+        );
+
+        let gh_ctx = MockGithubClient::new_context();
+        for expected_gist_id in ["source", "target"] {
+            gh_ctx.expect().in_sequence(&mut seq).returning(
+                move |gist_id, github_token, _options| {
+                    assert_eq!(gist_id, expected_gist_id);
+                    assert_eq!(github_token, "abcdefg");
+                    Ok(MockGithubClient::default())
+                },
+            );
+        }
+
+        let migrator_ctx = MockMigrator::new_context();
+        migrator_ctx.expect().in_sequence(&mut seq).returning(
+            |_source, _target, _io, history_received, _output| {
+                assert!(history_received);
+
+                let mut mock_migrator = MockMigrator::default();
+                mock_migrator
+                    .expect_migrate()
+                    .returning(|| Ok(Summary::default()));
+                mock_migrator
+            },
+        );
+
+        migrate(
+            &mut io,
+            &ConfigSource::default(),
+            "gist:source".parse()?,
+            "gist:target".parse()?,
+            true,
+            OutputFormat::Text,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_doctor_reports_failed_checks() -> Result<()> {
+        let mut io = MockInteractiveIO::default();
+
+        let doctor_ctx = MockDoctor::new_context();
+        doctor_ctx.expect().returning(|_io, output_received| {
+            assert_eq!(output_received, OutputFormat::Json);
+
+            let mut mock_doctor = MockDoctor::default();
+            mock_doctor.expect_run_checks().returning(|| {
+                Ok(Summary {
+                    succeeded: 4,
+                    skipped: 0,
+                    failed: 1,
+                })
+            });
+            mock_doctor
+        });
+
+        assert_eq!(doctor(&mut io, OutputFormat::Json).await?.failed, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_flags_are_parsed() {
+        let cli = Cli::parse_from([
+            "zed-settings-sync",
+            "push",
+            "--gist-id",
+            "1234567890",
+            "--token-env",
+            "MY_TOKEN",
+            "--output",
+            "json",
+        ]);
+
+        let config_source = cli.config_source();
+
+        assert_eq!(cli.output, OutputFormat::Json);
+
+        assert_eq!(
+            config_source.overrides.gist_id.as_deref(),
+            Some("1234567890")
+        );
+        assert_eq!(
+            config_source.overrides.token_env.as_deref(),
+            Some("MY_TOKEN")
+        );
+    }
+
+    #[test]
+    fn test_backups_are_listed() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 0);
+        backups.back_up(zed_config_dir().child("keymap.json").path())?;
+        let snapshot_name = backups.snapshots()?[0].name.clone();
+
+        let mut io = MockInteractiveIO::default();
+        io.expect_write_line()
+            .with(predicate::eq(format!("{snapshot_name}: keymap.json")))
+            .returning(|_| Ok(()))
+            .once();
+
+        restore_backup(&mut io, None, backups, OutputFormat::Text)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_events_are_filtered_by_file() -> Result<()> {
+        let log = SyncEventLog::new(&zed_paths::data_dir());
+        let event = SyncEvent::new(
+            SyncDirection::Push,
+            "settings.json",
+            "{}",
+            Some("abc123def456".to_string()),
+            None,
+        );
+        log.append(&event)?;
+        log.append(&SyncEvent::new(
+            SyncDirection::Load,
+            "keymap.json",
+            "[]",
+            None,
+            None,
+        ))?;
+
+        let mut io = MockInteractiveIO::default();
+        io.expect_write_line()
+            .with(predicate::eq(format!(
+                "{} push settings.json 2 bytes, hash {}, rev abc123d: ok",
+                event.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                &event.hash[..7]
+            )))
+            .returning(|_| Ok(()))
+            .once();
+
+        show_log(
+            &mut io,
+            &log,
+            &SyncEventFilter {
+                file: Some("settings.json".to_string()),
+                ..SyncEventFilter::default()
+            },
+            OutputFormat::Text,
+        )?;
+
+        Ok(())
     }
 }
@@ -0,0 +1,335 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{Result, bail};
+use common::{
+    interactive_io::InteractiveIO,
+    sync::{Client, file_digest},
+};
+use zed_extension_api::serde_json::{json, to_string};
+
+use crate::output::{EventReporter, FileEvent, OutputFormat, Summary};
+
+/// Storage of the synced files, given as `gist:<id>` or `archive:<path>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Provider {
+    Gist(String),
+    Archive(PathBuf),
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("gist", id)) if !id.is_empty() => Ok(Provider::Gist(id.to_string())),
+            Some(("archive", path)) if !path.is_empty() => {
+                Ok(Provider::Archive(PathBuf::from(path)))
+            }
+            _ => bail!("Unknown provider {s}, expected gist:<id> or archive:<path>"),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Gist(id) => write!(f, "gist:{id}"),
+            Provider::Archive(path) => write!(f, "archive:{}", path.display()),
+        }
+    }
+}
+
+/// Copies the stored files from one provider to another as they are, without materializing them locally
+pub struct Migrator<'a> {
+    source: &'a dyn Client,
+    target: &'a dyn Client,
+    io: &'a mut dyn InteractiveIO,
+    replay_history: bool,
+    reporter: EventReporter,
+}
+
+impl<'a> Migrator<'a> {
+    pub fn new(
+        source: &'a dyn Client,
+        target: &'a dyn Client,
+        io: &'a mut dyn InteractiveIO,
+        replay_history: bool,
+        output: OutputFormat,
+    ) -> Self {
+        Migrator {
+            source,
+            target,
+            io,
+            replay_history,
+            reporter: EventReporter::new(output),
+        }
+    }
+
+    pub async fn migrate(&mut self) -> Result<Summary> {
+        // the target files missing from the source are deleted, so both end up with the same files
+        let mut stored_file_names = self
+            .target
+            .load_raw_files(None)
+            .await?
+            .into_keys()
+            .collect();
+        if self.replay_history {
+            stored_file_names = self.replay_revisions(stored_file_names).await?;
+        }
+
+        let files = self.source.load_raw_files(None).await?;
+        self.store(files.clone(), &stored_file_names).await?;
+
+        self.verify(files).await
+    }
+
+    async fn replay_revisions(
+        &mut self,
+        mut stored_file_names: BTreeSet<String>,
+    ) -> Result<BTreeSet<String>> {
+        let mut revisions = self.source.revisions().await?;
+        // the latest revision is the one migrated afterwards
+        revisions.pop();
+
+        for revision in revisions {
+            let files = self.source.load_raw_files(Some(revision.clone())).await?;
+            stored_file_names = self.store(files, &stored_file_names).await?;
+
+            match self.reporter.format() {
+                OutputFormat::Text => self
+                    .io
+                    .write_line(&format!("Replayed revision {revision}"))?,
+                OutputFormat::Json => self.io.write_line(&to_string(
+                    &json!({ "event": "revision_replayed", "revision": revision }),
+                )?)?,
+            }
+        }
+
+        Ok(stored_file_names)
+    }
+
+    /// Stores the files in the target, deleting the previously stored ones that aren't among them
+    async fn store(
+        &self,
+        files: BTreeMap<String, String>,
+        stored_file_names: &BTreeSet<String>,
+    ) -> Result<BTreeSet<String>> {
+        let file_names: BTreeSet<_> = files.keys().cloned().collect();
+
+        self.target.store_raw_files(files).await?;
+        self.target
+            .delete_raw_files(stored_file_names - &file_names)
+            .await?;
+
+        Ok(file_names)
+    }
+
+    async fn verify(&mut self, files: BTreeMap<String, String>) -> Result<Summary> {
+        let mut stored_files = self.target.load_raw_files(None).await?;
+
+        for (file_name, content) in files {
+            let event = match stored_files.remove(&file_name) {
+                Some(stored) if file_digest(&stored) == file_digest(&content) => {
+                    FileEvent::Migrated { file_name }
+                }
+                Some(_) => FileEvent::error(
+                    file_name,
+                    "verification",
+                    "Stored content doesn't match the source one".to_string(),
+                ),
+                None => FileEvent::error(
+                    file_name,
+                    "verification",
+                    "File is missing in the target".to_string(),
+                ),
+            };
+            self.reporter.report(self.io, &event)?;
+        }
+
+        // left over from before the migration and failed to be deleted
+        for file_name in stored_files.into_keys() {
+            self.reporter.report(
+                self.io,
+                &FileEvent::error(
+                    file_name,
+                    "verification",
+                    "File is missing in the source".to_string(),
+                ),
+            )?;
+        }
+
+        Ok(self.reporter.summary())
+    }
+}
+
+#[cfg(test)]
+mockall::mock! {
+    pub Migrator {
+        pub fn new<'a>(source: &'a dyn Client, target: &'a dyn Client, io: &'a mut dyn InteractiveIO, replay_history: bool, output: OutputFormat) -> Self;
+        pub async fn migrate(&mut self) -> Result<Summary>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(dead_code)]
+
+    use anyhow::Result;
+    use common::{interactive_io::MockInteractiveIO, sync::MockGithubClient};
+    use mockall::{Sequence, predicate};
+
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(file_name, content)| ((*file_name).to_string(), (*content).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_providers_are_parsed() -> Result<()> {
+        assert_eq!(
+            "gist:abc".parse::<Provider>()?,
+            Provider::Gist("abc".to_string())
+        );
+        assert_eq!(
+            "archive:/tmp/settings.tar.gz".parse::<Provider>()?,
+            Provider::Archive(PathBuf::from("/tmp/settings.tar.gz"))
+        );
+        assert!("gist:".parse::<Provider>().is_err());
+        assert!("repo:abc".parse::<Provider>().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history_is_replayed_in_order_and_verified() -> Result<()> {
+        let mut seq = Sequence::new();
+
+        let mut source = MockGithubClient::default();
+        let mut target = MockGithubClient::default();
+        target
+            .expect_load_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(None))
+            .returning(|_| Ok(files(&[("README.md", "")])))
+            .once();
+        source
+            .expect_revisions()
+            .in_sequence(&mut seq)
+            .returning(|| Ok(vec!["r1".to_string(), "r2".to_string()]))
+            .once();
+        source
+            .expect_load_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(Some("r1".to_string())))
+            .returning(|_| Ok(files(&[("keymap.json", "[]")])))
+            .once();
+        target
+            .expect_store_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(files(&[("keymap.json", "[]")])))
+            .returning(|_| Ok(()))
+            .once();
+        target
+            .expect_delete_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(BTreeSet::from(["README.md".to_string()])))
+            .returning(|_| Ok(()))
+            .once();
+
+        let mut mock_io = MockInteractiveIO::default();
+        mock_io
+            .expect_write_line()
+            .in_sequence(&mut seq)
+            .with(predicate::eq("Replayed revision r1"))
+            .returning(|_| Ok(()))
+            .once();
+
+        let latest = files(&[("keymap.json", "[{}]"), ("tasks.json", "[]")]);
+        source
+            .expect_load_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(None))
+            .returning({
+                let latest = latest.clone();
+                move |_| Ok(latest.clone())
+            })
+            .once();
+        target
+            .expect_store_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(latest.clone()))
+            .returning(|_| Ok(()))
+            .once();
+        target
+            .expect_delete_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(BTreeSet::new()))
+            .returning(|_| Ok(()))
+            .once();
+        target
+            .expect_load_raw_files()
+            .in_sequence(&mut seq)
+            .with(predicate::eq(None))
+            .returning(move |_| Ok(latest.clone()))
+            .once();
+        for line in ["Migrated keymap.json", "Migrated tasks.json"] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut migrator = Migrator::new(&source, &target, &mut mock_io, true, OutputFormat::Text);
+        let summary = migrator.migrate().await?;
+
+        assert_eq!(summary.succeeded, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mismatching_files_are_reported_as_failed() -> Result<()> {
+        let mut source = MockGithubClient::default();
+        let mut target = MockGithubClient::default();
+        source.expect_revisions().never();
+        source
+            .expect_load_raw_files()
+            .returning(|_| Ok(files(&[("keymap.json", "[]"), ("tasks.json", "[]")])));
+        target.expect_store_raw_files().returning(|_| Ok(()));
+        // failing to delete the file that isn't in the source
+        target.expect_delete_raw_files().returning(|_| Ok(()));
+        target
+            .expect_load_raw_files()
+            .returning(|_| Ok(files(&[("keymap.json", "[{}]"), ("snippets.json", "{}")])));
+
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            r#"{"event":"error","file_name":"keymap.json","error":{"kind":"verification","message":"Stored content doesn't match the source one"}}"#,
+            r#"{"event":"error","file_name":"tasks.json","error":{"kind":"verification","message":"File is missing in the target"}}"#,
+            r#"{"event":"error","file_name":"snippets.json","error":{"kind":"verification","message":"File is missing in the source"}}"#,
+        ] {
+            mock_io
+                .expect_write_line()
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut migrator = Migrator::new(&source, &target, &mut mock_io, false, OutputFormat::Json);
+        let summary = migrator.migrate().await?;
+
+        assert_eq!(summary.failed, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fmt::{self, Display},
+    io,
+};
+
+use clap::ValueEnum;
+use common::{interactive_io::InteractiveIO, sync::FileError};
+use serde::Serialize;
+use zed_extension_api::serde_json::to_string;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Something that happened to a file while running a command
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FileEvent {
+    Written {
+        file_name: String,
+    },
+    Pushed {
+        file_name: String,
+    },
+    Restored {
+        file_name: String,
+    },
+    Exported {
+        file_name: String,
+    },
+    /// Stored by the target provider with the same content as in the source one
+    Migrated {
+        file_name: String,
+    },
+    /// Dry run result
+    Planned {
+        file_name: String,
+        change: PlannedChange,
+    },
+    Skipped {
+        file_name: String,
+        reason: SkipReason,
+    },
+    Error {
+        file_name: String,
+        error: ErrorDetails,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedChange {
+    Create,
+    Update,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The user declined overwriting the local file
+    Declined,
+    /// The local file exists and overwriting it wasn't forced
+    Exists,
+    NotFound,
+    /// The include/exclude rules of the configuration leave the file out of syncing
+    Excluded,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ErrorDetails {
+    kind: String,
+    message: String,
+}
+
+impl FileEvent {
+    /// Error event for a failure that didn't come from a [`Client`](common::sync::Client)
+    pub fn error(file_name: String, kind: &str, message: String) -> Self {
+        FileEvent::Error {
+            file_name,
+            error: ErrorDetails {
+                kind: kind.to_string(),
+                message,
+            },
+        }
+    }
+}
+
+impl From<&FileError> for FileEvent {
+    fn from(e: &FileError) -> Self {
+        FileEvent::Error {
+            file_name: e.file_name().to_string(),
+            error: ErrorDetails {
+                kind: e.error().kind().to_string(),
+                message: e.error().to_string(),
+            },
+        }
+    }
+}
+
+impl Display for FileEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileEvent::Written { file_name } => write!(f, "Written {file_name}"),
+            FileEvent::Pushed { file_name } => write!(f, "Pushed {file_name}"),
+            FileEvent::Restored { file_name } => write!(f, "Restored {file_name}"),
+            FileEvent::Exported { file_name } => write!(f, "Exported {file_name}"),
+            FileEvent::Migrated { file_name } => write!(f, "Migrated {file_name}"),
+            FileEvent::Planned { file_name, change } => match change {
+                PlannedChange::Create => write!(f, "Would create {file_name}"),
+                PlannedChange::Update => write!(f, "Would update {file_name}"),
+                PlannedChange::None => write!(f, "Unchanged {file_name}"),
+            },
+            FileEvent::Skipped { file_name, reason } => match reason {
+                SkipReason::Declined => write!(f, "Skipping {file_name}"),
+                SkipReason::Exists => write!(f, "🟡 Skipping {file_name}, it exists"),
+                SkipReason::NotFound => write!(f, "🟡 {file_name} not found in the gist"),
+                SkipReason::Excluded => {
+                    write!(f, "🟡 Skipping {file_name}, it's excluded from sync")
+                }
+            },
+            // same text as the `FileError` the event was built from
+            FileEvent::Error { file_name, error } => {
+                write!(f, "🔴 Error syncing file {file_name}: {}", error.message)
+            }
+        }
+    }
+}
+
+/// Outcome counts of a command processing files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub succeeded: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// Writes file events in the requested format, counting them into a [`Summary`]
+#[derive(Debug, Default)]
+pub struct EventReporter {
+    format: OutputFormat,
+    summary: Summary,
+}
+
+impl EventReporter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            summary: Summary::default(),
+        }
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    pub fn report(&mut self, io: &mut dyn InteractiveIO, event: &FileEvent) -> io::Result<()> {
+        match event {
+            FileEvent::Skipped { .. } => self.summary.skipped += 1,
+            FileEvent::Error { .. } => self.summary.failed += 1,
+            _ => self.summary.succeeded += 1,
+        }
+
+        match self.format {
+            OutputFormat::Text => io.write_line(&event.to_string()),
+            OutputFormat::Json => io.write_line(&to_string(event)?),
+        }
+    }
+}
+
+/// Writes the final line of a command run
+pub fn write_summary(
+    io: &mut dyn InteractiveIO,
+    format: OutputFormat,
+    summary: Summary,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text if summary.failed > 0 => {
+            io.write_line(&format!("🟠 Done, {} file(s) failed.", summary.failed))
+        }
+        OutputFormat::Text => io.write_line("🟢 All done."),
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct SummaryEvent {
+                event: &'static str,
+                #[serde(flatten)]
+                summary: Summary,
+            }
+
+            io.write_line(&to_string(&SummaryEvent {
+                event: "summary",
+                summary,
+            })?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::{
+        interactive_io::MockInteractiveIO,
+        sync::{Error, FileError},
+    };
+    use mockall::{Sequence, predicate};
+
+    use super::*;
+
+    #[test]
+    fn test_events_are_written_as_json_lines_and_counted() -> io::Result<()> {
+        let mut seq = Sequence::new();
+        let mut mock_io = MockInteractiveIO::default();
+        for line in [
+            r#"{"event":"written","file_name":"keymap.json"}"#,
+            r#"{"event":"skipped","file_name":"tasks.json","reason":"exists"}"#,
+            r#"{"event":"error","file_name":"debug.json","error":{"kind":"unhandled_internal","message":"Unhandled internal error from underlying client library: boom"}}"#,
+            r#"{"event":"summary","succeeded":1,"skipped":1,"failed":1}"#,
+        ] {
+            mock_io
+                .expect_write_line()
+                .in_sequence(&mut seq)
+                .with(predicate::eq(line))
+                .returning(|_| Ok(()))
+                .once();
+        }
+
+        let mut reporter = EventReporter::new(OutputFormat::Json);
+        reporter.report(
+            &mut mock_io,
+            &FileEvent::Written {
+                file_name: "keymap.json".to_string(),
+            },
+        )?;
+        reporter.report(
+            &mut mock_io,
+            &FileEvent::Skipped {
+                file_name: "tasks.json".to_string(),
+                reason: SkipReason::Exists,
+            },
+        )?;
+        reporter.report(
+            &mut mock_io,
+            &FileEvent::from(&FileError::from_error(
+                "debug.json",
+                Error::UnhandledInternal("boom".to_string()),
+            )),
+        )?;
+
+        write_summary(&mut mock_io, OutputFormat::Json, reporter.summary())
+    }
+
+    #[test]
+    fn test_error_event_text_matches_file_error() {
+        let error = FileError::from_error("debug.json", Error::InvalidConfig("oops".to_string()));
+
+        assert_eq!(FileEvent::from(&error).to_string(), format!("🔴 {error}"));
+    }
+}
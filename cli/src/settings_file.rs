@@ -0,0 +1,136 @@
+use std::fs;
+
+use anyhow::{Result, anyhow};
+use common::sync::BackupStore;
+use jsonc_parser::{
+    ParseOptions,
+    cst::{CstInputValue, CstRootNode},
+    parse_to_serde_value,
+};
+#[cfg(not(test))]
+use paths as zed_paths;
+#[cfg(test)]
+use test_support::zed_paths;
+
+const LSP_KEY: &str = "lsp";
+const LSP_SERVER_KEY: &str = "settings-sync";
+const INITIALIZATION_OPTIONS_KEY: &str = "initialization_options";
+
+pub fn has_initialization_options() -> Result<bool> {
+    let Some(content) = read_settings_file()? else {
+        return Ok(false);
+    };
+
+    Ok(parse_to_serde_value(&content, &ParseOptions::default())?
+        .and_then(|settings| {
+            settings
+                .pointer(&format!(
+                    "/{LSP_KEY}/{LSP_SERVER_KEY}/{INITIALIZATION_OPTIONS_KEY}"
+                ))
+                .cloned()
+        })
+        .is_some())
+}
+
+/// Adds the extension configuration to the settings file, keeping the rest of it (including comments) intact
+pub fn write_initialization_options(
+    gist_id: &str,
+    github_token: &str,
+    backups: &mut BackupStore,
+) -> Result<()> {
+    let content = read_settings_file()?.unwrap_or_else(|| "{}\n".to_string());
+    let root = CstRootNode::parse(&content, &ParseOptions::default())?;
+
+    let options = root
+        .object_value_or_set()
+        .object_value_or_set(LSP_KEY)
+        .object_value_or_set(LSP_SERVER_KEY)
+        .object_value_or_set(INITIALIZATION_OPTIONS_KEY);
+    for (key, value) in [("github_token", github_token), ("gist_id", gist_id)] {
+        let value = CstInputValue::String(value.to_string());
+        match options.get(key) {
+            Some(prop) => prop.set_value(value),
+            None => {
+                options.append(key, value);
+            }
+        }
+    }
+
+    let settings_file = zed_paths::settings_file();
+    if settings_file.try_exists()? {
+        backups.back_up(&settings_file)?;
+    }
+    fs::write(settings_file, root.to_string())?;
+
+    Ok(())
+}
+
+fn read_settings_file() -> Result<Option<String>> {
+    let settings_file = zed_paths::settings_file();
+
+    if !settings_file.try_exists()? {
+        return Ok(None);
+    }
+
+    fs::read_to_string(&settings_file)
+        .map(Some)
+        .map_err(|err| anyhow!("Failed to read {}: {err}", settings_file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use test_support::zed_settings_file;
+    use zed_extension_api::serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_options_are_added_to_settings_file_keeping_comments() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"{
+  // my theme
+  "theme": "One Dark",
+  "lsp": {
+    "rust-analyzer": {}
+  }
+}
+"#,
+        )?;
+
+        assert!(!has_initialization_options()?);
+
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 0);
+        write_initialization_options("deadbeef", "gho_token", &mut backups)?;
+
+        assert!(has_initialization_options()?);
+        assert_eq!(backups.snapshots()?[0].file_names, ["settings.json"]);
+        let content = fs::read_to_string(zed_settings_file().path())?;
+        assert!(content.contains("// my theme"));
+        assert!(content.contains(r#""rust-analyzer": {}"#));
+        assert_eq!(
+            parse_to_serde_value(&content, &ParseOptions::default())?.and_then(|settings| {
+                settings
+                    .pointer("/lsp/settings-sync/initialization_options")
+                    .cloned()
+            }),
+            Some(json!({ "github_token": "gho_token", "gist_id": "deadbeef" }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_settings_file_is_created_if_missing() -> Result<()> {
+        assert!(!has_initialization_options()?);
+
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 0);
+        write_initialization_options("deadbeef", "gho_token", &mut backups)?;
+
+        assert!(has_initialization_options()?);
+        assert!(backups.snapshots()?.is_empty());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use std::fs;
+use std::{env, fs};
 
 use anyhow::{Result, anyhow, bail};
 use jsonc_parser::{ParseOptions, parse_to_serde_value};
@@ -11,14 +11,80 @@ use serde::Deserialize;
 use test_support::read_password;
 #[cfg(test)]
 use test_support::zed_paths;
-use zed_extension_api::serde_json::from_value;
+use zed_extension_api::serde_json::{Value, from_value};
 
-use crate::interactive_io::InteractiveIO;
+use crate::{
+    interactive_io::InteractiveIO,
+    sync::{FileFilter, Profile, SyncOptions, current_hostname},
+};
 
-#[derive(Debug, Deserialize)]
+pub const GIST_ID_ENV_VAR: &str = "ZED_SETTINGS_SYNC_GIST_ID";
+pub const GITHUB_TOKEN_ENV_VAR: &str = "ZED_SETTINGS_SYNC_GITHUB_TOKEN";
+
+const INITIALIZATION_OPTIONS_POINTER: &str = "/lsp/settings-sync/initialization_options";
+
+/// Configuration values given explicitly, e.g. with command line flags
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub gist_id: Option<String>,
+    /// Name of the environment variable holding the Github token
+    pub token_env: Option<String>,
+}
+
+/// Keys of `lsp.settings-sync.initialization_options` in the Zed settings file, see [`Config`]
+pub const CONFIG_KEYS: [&str; 8] = [
+    "gist_id",
+    "github_token",
+    "include",
+    "exclude",
+    "profile",
+    "machine_local_keys",
+    "notifications",
+    "sync_trigger",
+];
+
+/// When the language server shows a popup after syncing a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncNotifications {
+    #[default]
+    Always,
+    Never,
+    /// The first successful sync of the server run and all failures
+    OnFirstSync,
+    ErrorsOnly,
+}
+
+/// What makes the language server sync a config file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncTrigger {
+    /// File system events of the open config files
+    #[default]
+    Watcher,
+    /// Saves in the editor, file system events only for the changes made outside of it
+    Save,
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Config {
     gist_id: String,
     github_token: String,
+    /// Glob patterns of config files to sync, relative to Zed config dir; all files if empty
+    #[serde(default)]
+    include: Vec<String>,
+    /// Glob patterns of config files to never sync, relative to Zed config dir
+    #[serde(default)]
+    exclude: Vec<String>,
+    /// Name of the per-machine settings overlay; the hostname if not set
+    profile: Option<String>,
+    /// Dotted paths of settings.json keys stored in the per-machine overlay instead of the shared base
+    #[serde(default)]
+    machine_local_keys: Vec<String>,
+    #[serde(default)]
+    notifications: SyncNotifications,
+    #[serde(default)]
+    sync_trigger: SyncTrigger,
 }
 
 #[allow(clippy::missing_errors_doc)]
@@ -35,6 +101,26 @@ impl Config {
         &self.github_token
     }
 
+    #[must_use]
+    pub fn notifications(&self) -> SyncNotifications {
+        self.notifications
+    }
+
+    #[must_use]
+    pub fn sync_trigger(&self) -> SyncTrigger {
+        self.sync_trigger
+    }
+
+    pub fn sync_options(&self) -> Result<SyncOptions> {
+        Ok(SyncOptions {
+            file_filter: FileFilter::new(&self.include, &self.exclude)?,
+            profile: Profile::new(
+                self.profile.clone().unwrap_or_else(current_hostname),
+                self.machine_local_keys.clone(),
+            ),
+        })
+    }
+
     pub fn from_settings_file() -> Result<Self> {
         // we don't care about possible TOCTOU errors because if Zed is installed, its config key is guaranteed to exist
         if !zed_paths::settings_file().try_exists()? {
@@ -48,7 +134,7 @@ impl Config {
             .ok_or(anyhow!("Settings file is empty"))?;
         let config = from_value(
             zed_settings
-                .pointer("/lsp/settings-sync/initialization_options") // TODO: make this pointer key shared among crates of this package
+                .pointer(INITIALIZATION_OPTIONS_POINTER) // TODO: make this pointer key shared among crates of this package
                 .ok_or(anyhow!(
                     "Missing lsp.settings-sync.initialization_options key in settings tree"
                 ))?
@@ -59,6 +145,27 @@ impl Config {
     }
 
     pub fn from_interactive_io<T: InteractiveIO + 'static>(io: &mut T) -> Result<Self> {
+        let github_token = Self::read_github_token(io)?;
+        let gist_id = Self::read_gist_id(io)?;
+
+        Ok(Config {
+            gist_id,
+            github_token,
+            ..Config::default()
+        })
+    }
+
+    /// Resolves the configuration from, in order of precedence: overrides, environment variables,
+    /// the settings file and, if allowed, an interactive prompt for the values still missing
+    pub fn resolve<T: InteractiveIO + 'static>(
+        io: &mut T,
+        overrides: &ConfigOverrides,
+        interactive: bool,
+    ) -> Result<Self> {
+        Self::resolve_with_env(io, overrides, interactive, &|name| env::var(name).ok())
+    }
+
+    pub fn read_github_token<T: InteractiveIO + 'static>(io: &mut T) -> Result<String> {
         io.write_line("Enter your Github token:")?;
         let mut github_token: String;
 
@@ -68,6 +175,107 @@ impl Config {
             github_token = read_password()?;
         }
 
+        Ok(github_token)
+    }
+}
+
+impl Config {
+    fn resolve_with_env<T: InteractiveIO + 'static>(
+        io: &mut T,
+        overrides: &ConfigOverrides,
+        interactive: bool,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let env_value = |name: &str| env(name).filter(|value| !value.is_empty());
+        let settings_config = Self::from_settings_file_if_configured()?;
+
+        let gist_id = overrides
+            .gist_id
+            .clone()
+            .or_else(|| env_value(GIST_ID_ENV_VAR))
+            .or_else(|| {
+                settings_config
+                    .as_ref()
+                    .map(|config| config.gist_id.clone())
+                    .filter(|gist_id| !gist_id.is_empty())
+            });
+        let github_token = match &overrides.token_env {
+            Some(name) => Some(env_value(name).ok_or_else(|| {
+                anyhow!("Environment variable {name} with the Github token is not set")
+            })?),
+            None => env_value(GITHUB_TOKEN_ENV_VAR).or_else(|| {
+                settings_config
+                    .as_ref()
+                    .map(|config| config.github_token.clone())
+                    .filter(|github_token| !github_token.is_empty())
+            }),
+        };
+
+        if !interactive {
+            let missing: Vec<_> = [
+                (github_token.is_none(), "Github token"),
+                (gist_id.is_none(), "Gist ID"),
+            ]
+            .into_iter()
+            .filter_map(|(is_missing, name)| is_missing.then_some(name))
+            .collect();
+
+            if !missing.is_empty() {
+                bail!(
+                    "Missing {}: pass --gist-id and --token-env flags, set {GIST_ID_ENV_VAR} and {GITHUB_TOKEN_ENV_VAR} \
+                     environment variables or configure the extension in your Zed settings file",
+                    missing.join(" and ")
+                );
+            }
+        }
+
+        let mut config = settings_config.unwrap_or_default();
+        config.github_token = match github_token {
+            Some(github_token) => github_token,
+            None => Self::read_github_token(io)?,
+        };
+        config.gist_id = match gist_id {
+            Some(gist_id) => gist_id,
+            None => Self::read_gist_id(io)?,
+        };
+
+        Ok(config)
+    }
+
+    /// Like [`Config::from_settings_file`], but `None` if there is no settings file or no extension configuration in it;
+    /// the Gist ID and the Github token may be missing there, as they can come from elsewhere
+    fn from_settings_file_if_configured() -> Result<Option<Self>> {
+        let settings_file = zed_paths::settings_file();
+        if !settings_file.try_exists()? {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&settings_file)?;
+        let zed_settings = parse_to_serde_value(&content, &ParseOptions::default())
+            .map_err(|err| anyhow!("Failed to parse {}: {err}", settings_file.display()))?;
+        let Some(mut options) = zed_settings
+            .and_then(|settings| settings.pointer(INITIALIZATION_OPTIONS_POINTER).cloned())
+        else {
+            return Ok(None);
+        };
+
+        if let Some(options) = options.as_object_mut() {
+            for key in ["gist_id", "github_token"] {
+                options
+                    .entry(key)
+                    .or_insert_with(|| Value::String(String::new()));
+            }
+        }
+        let config = from_value(options).map_err(|err| {
+            anyhow!(
+                "Invalid lsp.settings-sync.initialization_options in {}: {err}",
+                settings_file.display()
+            )
+        })?;
+
+        Ok(Some(config))
+    }
+
+    fn read_gist_id<T: InteractiveIO + 'static>(io: &mut T) -> Result<String> {
         io.write_line("Enter your Gist ID:")?;
         let mut gist_id = String::default();
         io.read_line(&mut gist_id)?;
@@ -79,10 +287,7 @@ impl Config {
             gist_id = gist_id.trim_end().to_owned();
         }
 
-        Ok(Config {
-            gist_id,
-            github_token,
-        })
+        Ok(gist_id)
     }
 }
 
@@ -93,7 +298,10 @@ impl Config {
 #[allow(clippy::unwrap_used)]
 #[cfg(test)]
 mod tests {
-    use std::io::{self, BufRead, Cursor, Seek, Write};
+    use std::{
+        io::{self, BufRead, Cursor, Seek, Write},
+        path::Path,
+    };
 
     use assert_fs::prelude::*;
     use test_support::{FAKE_GITHUB_TOKEN, zed_settings_file};
@@ -163,6 +371,91 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_from_file_with_sync_rules_success() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"
+            {
+                "lsp": {
+                    "settings-sync": {
+                        "initialization_options": {
+                            "github_token": "your_github_token",
+                            "gist_id": "your_gist_id",
+                            "include": ["*.json"],
+                            "exclude": ["debug.json"]
+                        }
+                    }
+                }
+            }
+            "#,
+        )?;
+
+        let config = Config::from_settings_file().expect("Failed to read config from file");
+        let file_filter = config.sync_options()?.file_filter;
+
+        assert_eq!(config.include, vec!["*.json"]);
+        assert_eq!(config.exclude, vec!["debug.json"]);
+        assert!(file_filter.matches(Path::new("keymap.json")));
+        assert!(!file_filter.matches(Path::new("debug.json")));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_from_file_with_profile_success() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"
+            {
+                "lsp": {
+                    "settings-sync": {
+                        "initialization_options": {
+                            "github_token": "your_github_token",
+                            "gist_id": "your_gist_id",
+                            "profile": "desktop",
+                            "machine_local_keys": ["buffer_font_size", "terminal.shell"]
+                        }
+                    }
+                }
+            }
+            "#,
+        )?;
+
+        let config = Config::from_settings_file().expect("Failed to read config from file");
+        let profile = config.sync_options()?.profile;
+
+        assert_eq!(profile.name(), "desktop");
+        assert_eq!(profile.overlay_file_name(), "settings.desktop.json");
+        assert!(profile.has_machine_local_keys());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_from_file_with_default_profile_success() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"
+            {
+                "lsp": {
+                    "settings-sync": {
+                        "initialization_options": {
+                            "github_token": "your_github_token",
+                            "gist_id": "your_gist_id"
+                        }
+                    }
+                }
+            }
+            "#,
+        )?;
+
+        let config = Config::from_settings_file().expect("Failed to read config from file");
+        let profile = config.sync_options()?.profile;
+
+        assert_eq!(profile.name(), current_hostname());
+        assert!(!profile.has_machine_local_keys());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_from_file_failure_when_settings_file_is_missing() {
         let config = Config::from_settings_file();
@@ -311,4 +604,138 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_resolve_prefers_overrides_and_env_vars_over_settings_file() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"
+            {
+                "lsp": {
+                    "settings-sync": {
+                        "initialization_options": {
+                            "github_token": "settings_token",
+                            "gist_id": "settings_gist_id",
+                            "exclude": ["debug.json"]
+                        }
+                    }
+                }
+            }
+            "#,
+        )?;
+        let mut io = CursorInteractiveIO::new("");
+        let overrides = ConfigOverrides {
+            gist_id: Some("flag_gist_id".to_string()),
+            token_env: Some("MY_TOKEN".to_string()),
+        };
+        let env = |name: &str| match name {
+            "MY_TOKEN" => Some("env_token".to_string()),
+            GIST_ID_ENV_VAR => Some("env_gist_id".to_string()),
+            _ => None,
+        };
+
+        let config = Config::resolve_with_env(&mut io, &overrides, false, &env)?;
+
+        assert_eq!(config.gist_id, "flag_gist_id");
+        assert_eq!(config.github_token, "env_token");
+        assert_eq!(config.exclude, vec!["debug.json"]);
+
+        let config = Config::resolve_with_env(&mut io, &ConfigOverrides::default(), false, &env)?;
+
+        assert_eq!(config.gist_id, "env_gist_id");
+        assert_eq!(config.github_token, "settings_token");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_fails_on_malformed_settings_file() -> Result<()> {
+        zed_settings_file().write_str(r#"{ "lsp": { "settings-sync": "#)?;
+        let mut io = CursorInteractiveIO::new("");
+        let env = |name: &str| match name {
+            GIST_ID_ENV_VAR => Some("env_gist_id".to_string()),
+            GITHUB_TOKEN_ENV_VAR => Some("env_token".to_string()),
+            _ => None,
+        };
+
+        let config = Config::resolve_with_env(&mut io, &ConfigOverrides::default(), false, &env);
+
+        assert!(
+            config
+                .unwrap_err()
+                .to_string()
+                .starts_with("Failed to parse ")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_keeps_settings_file_options_without_gist_id_and_token() -> Result<()> {
+        zed_settings_file().write_str(
+            r#"{ "lsp": { "settings-sync": { "initialization_options": { "exclude": ["debug.json"] } } } }"#,
+        )?;
+        let mut io = CursorInteractiveIO::new("");
+        let env = |name: &str| match name {
+            GIST_ID_ENV_VAR => Some("env_gist_id".to_string()),
+            GITHUB_TOKEN_ENV_VAR => Some("env_token".to_string()),
+            _ => None,
+        };
+
+        let config = Config::resolve_with_env(&mut io, &ConfigOverrides::default(), false, &env)?;
+
+        assert_eq!(config.gist_id, "env_gist_id");
+        assert_eq!(config.github_token, "env_token");
+        assert_eq!(config.exclude, vec!["debug.json"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_fails_without_config_when_not_interactive() {
+        let mut io = CursorInteractiveIO::new("");
+        let env = |name: &str| (name == GIST_ID_ENV_VAR).then(|| "env_gist_id".to_string());
+
+        let config = Config::resolve_with_env(&mut io, &ConfigOverrides::default(), false, &env);
+
+        assert!(
+            config
+                .unwrap_err()
+                .to_string()
+                .starts_with("Missing Github token: pass --gist-id and --token-env flags")
+        );
+    }
+
+    #[test]
+    fn test_resolve_fails_when_token_env_var_is_not_set() {
+        let mut io = CursorInteractiveIO::new("");
+        let overrides = ConfigOverrides {
+            gist_id: None,
+            token_env: Some("MY_TOKEN".to_string()),
+        };
+
+        let config = Config::resolve_with_env(&mut io, &overrides, true, &|_| None);
+
+        assert_eq!(
+            config.unwrap_err().to_string(),
+            "Environment variable MY_TOKEN with the Github token is not set"
+        );
+    }
+
+    #[test]
+    fn test_resolve_prompts_for_missing_values_when_interactive() -> Result<()> {
+        let mut io = CursorInteractiveIO::new("abcdef1234567890\n");
+        let env = |name: &str| (name == GITHUB_TOKEN_ENV_VAR).then(|| "env_token".to_string());
+
+        let config = Config::resolve_with_env(&mut io, &ConfigOverrides::default(), true, &env)?;
+
+        io.rewind_output()?;
+        let mut output_lines_iter = io.output_lines();
+
+        assert_eq!(output_lines_iter.next().unwrap()?, "Enter your Gist ID:");
+        assert!(output_lines_iter.next().is_none());
+        assert_eq!(config.github_token, "env_token");
+        assert_eq!(config.gist_id, "abcdef1234567890");
+
+        Ok(())
+    }
 }
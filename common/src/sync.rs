@@ -1,7 +1,27 @@
+mod archive;
+mod backups;
 mod client;
 mod error;
+mod event_log;
+mod file_filter;
 mod local_file_data;
+mod local_file_writer;
+mod options;
+mod os_sections;
+mod profile;
+mod state;
+mod templating;
 
+pub use archive::*;
+pub use backups::*;
 pub use client::*;
 pub use error::*;
+pub use event_log::*;
+pub use file_filter::*;
 pub use local_file_data::*;
+pub use local_file_writer::*;
+pub use options::*;
+pub use os_sections::*;
+pub use profile::*;
+pub use state::*;
+pub use templating::*;
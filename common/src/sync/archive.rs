@@ -0,0 +1,218 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Archive, Builder, Header};
+use zed_extension_api::serde_json::{from_str, to_string_pretty};
+
+use crate::{
+    ZED_CONFIG_FILE_NAME,
+    sync::{
+        CURRENT_OS, LocalFileData, SyncOptions, TemplateVariables,
+        client::remote_files::transform_file_body_for_remote, has_os_sections,
+        restore_foreign_os_sections,
+    },
+};
+
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    /// Version of the tool that created the archive
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// SHA-256 of each archived file, see [`file_digest`]
+    pub files: BTreeMap<String, String>,
+}
+
+/// Writes the files into a tar.gz archive, transformed the same way as when they are stored in a gist.
+/// The sections for other OSes, emptied in the local files, are taken from the stored files.
+#[allow(clippy::missing_errors_doc)]
+pub fn export_archive(
+    path: &Path,
+    files: &[LocalFileData],
+    stored_files: &BTreeMap<String, String>,
+    options: &SyncOptions,
+    created_by: &str,
+) -> Result<ArchiveManifest> {
+    let template_variables = TemplateVariables::local();
+    let mut entries = BTreeMap::new();
+
+    for data in files {
+        let local_body = match stored_files.get(&data.filename) {
+            Some(stored_body) if has_os_sections(&data.body) => {
+                restore_foreign_os_sections(&data.body, stored_body, CURRENT_OS)
+                    .with_context(|| format!("Failed to export {}", data.filename))?
+            }
+            _ => data.body.clone(),
+        };
+        let (body, profile_overlay) = transform_file_body_for_remote(
+            &local_body,
+            data.filename == *ZED_CONFIG_FILE_NAME,
+            options,
+            &template_variables,
+        )
+        .with_context(|| format!("Failed to export {}", data.filename))?;
+
+        entries.insert(data.filename.clone(), body);
+        if let Some(overlay) = profile_overlay {
+            entries.insert(options.profile.overlay_file_name(), overlay);
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        created_by: created_by.to_string(),
+        created_at: Utc::now(),
+        files: entries
+            .iter()
+            .map(|(file_name, body)| (file_name.clone(), file_digest(body)))
+            .collect(),
+    };
+
+    let mut builder = Builder::new(GzEncoder::new(
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+        Compression::default(),
+    ));
+    append_entry(
+        &mut builder,
+        MANIFEST_FILE_NAME,
+        &to_string_pretty(&manifest)?,
+    )?;
+    for (file_name, body) in &entries {
+        append_entry(&mut builder, file_name, body)?;
+    }
+    builder.into_inner()?.finish()?;
+
+    Ok(manifest)
+}
+
+/// Reads the manifest and the files of an archive written by [`export_archive`]
+#[allow(clippy::missing_errors_doc)]
+pub fn read_archive(path: &Path) -> Result<(ArchiveManifest, BTreeMap<String, String>)> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    let mut manifest = None;
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .with_context(|| format!("Failed to read {name} from the archive"))?;
+
+        if name == MANIFEST_FILE_NAME {
+            manifest = Some(from_str::<ArchiveManifest>(&content)?);
+        } else {
+            files.insert(name, content);
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        bail!("{} has no {MANIFEST_FILE_NAME}", path.display());
+    };
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        bail!(
+            "Archive format version {} is not supported, please update the CLI tool",
+            manifest.format_version
+        );
+    }
+
+    Ok((manifest, files))
+}
+
+#[must_use]
+pub fn file_digest(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn append_entry<W: Write>(builder: &mut Builder<W>, name: &str, content: &str) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(u64::try_from(content.len())?);
+    header.set_mode(0o644);
+    header.set_mtime(u64::try_from(Utc::now().timestamp()).unwrap_or_default());
+    header.set_cksum();
+    builder.append_data(&mut header, name, content.as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_exported_files_are_masked_and_listed_in_manifest() -> Result<()> {
+        let dir = TempDir::new()?;
+        let archive_path = dir.path().join("settings.tar.gz");
+        let settings = LocalFileData::new(
+            dir.path().join("settings.json"),
+            r#"{ "lsp": { "settings-sync": { "initialization_options": { "github_token": "gho_secret" } } } }"#
+                .to_string(),
+        )?;
+        let keymap = LocalFileData::new(dir.path().join("keymap.json"), "[]".to_string())?;
+
+        let manifest = export_archive(
+            &archive_path,
+            &[settings, keymap],
+            &BTreeMap::new(),
+            &SyncOptions::default(),
+            "1.2.3",
+        )?;
+        let (read_manifest, files) = read_archive(&archive_path)?;
+
+        assert_eq!(read_manifest, manifest);
+        assert_eq!(manifest.created_by, "1.2.3");
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            ["keymap.json", "settings.json"]
+        );
+        assert!(!files["settings.json"].contains("gho_secret"));
+        assert!(files["settings.json"].contains("[masked]"));
+        assert_eq!(
+            manifest.files["keymap.json"],
+            file_digest(&files["keymap.json"])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exported_files_keep_foreign_os_sections_and_unconfigured_settings() -> Result<()> {
+        let dir = TempDir::new()?;
+        let archive_path = dir.path().join("settings.tar.gz");
+        let section = |content: &str| format!("{{\n  // @os plan9\n{content}  // @end-os\n}}\n");
+        let settings = LocalFileData::new(dir.path().join("settings.json"), section(""))?;
+        let stored_files = BTreeMap::from([(
+            "settings.json".to_string(),
+            section("  \"theme\": \"Plan 9\"\n"),
+        )]);
+
+        export_archive(
+            &archive_path,
+            &[settings],
+            &stored_files,
+            &SyncOptions::default(),
+            "1.2.3",
+        )?;
+        let (_, files) = read_archive(&archive_path)?;
+
+        assert_eq!(files["settings.json"], stored_files["settings.json"]);
+
+        Ok(())
+    }
+}
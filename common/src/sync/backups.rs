@@ -0,0 +1,244 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use serde::Serialize;
+
+/// Number of snapshots kept unless configured otherwise
+pub const DEFAULT_BACKUP_RETENTION: usize = 10;
+
+const BACKUPS_DIR: &str = "settings-sync/backups";
+// sortable and valid as a directory name on all platforms
+const SNAPSHOT_NAME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S%.3f";
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub file_names: Vec<String>,
+}
+
+/// Snapshots of local config files taken before they are overwritten, one snapshot per run
+#[derive(Debug)]
+pub struct BackupStore {
+    backups_dir: PathBuf,
+    // number of snapshots to keep, 0 keeps all of them
+    retention: usize,
+    current_snapshot_dir: Option<PathBuf>,
+}
+
+impl BackupStore {
+    #[must_use]
+    pub fn new(data_dir: &Path, retention: usize) -> Self {
+        Self {
+            backups_dir: data_dir.join(BACKUPS_DIR),
+            retention,
+            current_snapshot_dir: None,
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn back_up(&mut self, file_path: &Path) -> Result<()> {
+        let file_name = file_path
+            .file_name()
+            .ok_or_else(|| anyhow!("Invalid file path: {}", file_path.display()))?;
+
+        let snapshot_dir = match &self.current_snapshot_dir {
+            Some(snapshot_dir) => snapshot_dir.clone(),
+            None => self.create_snapshot_dir()?,
+        };
+        fs::copy(file_path, snapshot_dir.join(file_name))?;
+
+        Ok(())
+    }
+
+    /// Lists snapshots, newest first
+    #[allow(clippy::missing_errors_doc)]
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        let mut snapshots = Vec::new();
+
+        for name in self.snapshot_names()?.into_iter().rev() {
+            let mut file_names = Vec::new();
+            for entry in fs::read_dir(self.backups_dir.join(&name))? {
+                file_names.push(entry?.file_name().to_string_lossy().into_owned());
+            }
+            file_names.sort();
+
+            snapshots.push(Snapshot { name, file_names });
+        }
+
+        Ok(snapshots)
+    }
+
+    /// Copies the files of the snapshot to the Zed config dir, backing up the files it overwrites
+    #[allow(clippy::missing_errors_doc)]
+    pub fn restore(&mut self, snapshot_name: &str, config_dir: &Path) -> Result<Vec<String>> {
+        let Some(snapshot) = self
+            .snapshots()?
+            .into_iter()
+            .find(|snapshot| snapshot.name == snapshot_name)
+        else {
+            bail!("Backup {snapshot_name} not found");
+        };
+
+        // reading the snapshot first, as backing up the overwritten files may prune it
+        let snapshot_dir = self.backups_dir.join(&snapshot.name);
+        let contents = snapshot
+            .file_names
+            .iter()
+            .map(|file_name| fs::read(snapshot_dir.join(file_name)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (file_name, content) in snapshot.file_names.iter().zip(contents) {
+            let file_path = config_dir.join(file_name);
+            if file_path.exists() {
+                self.back_up(&file_path)?;
+            }
+            fs::write(file_path, content)?;
+        }
+
+        Ok(snapshot.file_names)
+    }
+
+    fn create_snapshot_dir(&mut self) -> Result<PathBuf> {
+        let base_name = Utc::now().format(SNAPSHOT_NAME_FORMAT).to_string();
+        let mut snapshot_dir = self.backups_dir.join(&base_name);
+        let mut suffix = 1;
+        while snapshot_dir.exists() {
+            snapshot_dir = self.backups_dir.join(format!("{base_name}-{suffix}"));
+            suffix += 1;
+        }
+
+        fs::create_dir_all(&snapshot_dir)?;
+        self.current_snapshot_dir = Some(snapshot_dir.clone());
+        self.prune()?;
+
+        Ok(snapshot_dir)
+    }
+
+    fn prune(&self) -> Result<()> {
+        if self.retention == 0 {
+            return Ok(());
+        }
+
+        let names = self.snapshot_names()?;
+        for name in names
+            .iter()
+            .take(names.len().saturating_sub(self.retention))
+        {
+            fs::remove_dir_all(self.backups_dir.join(name))?;
+        }
+
+        Ok(())
+    }
+
+    // sorted oldest first
+    fn snapshot_names(&self) -> Result<Vec<String>> {
+        if !self.backups_dir.try_exists()? {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.backups_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use anyhow::Result;
+    use assert_fs::prelude::*;
+    use test_support::{zed_config_dir, zed_paths};
+
+    use super::*;
+
+    #[test]
+    fn test_files_of_a_run_are_backed_up_into_one_snapshot() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+        zed_config_dir().child("tasks.json").write_str("[{}]")?;
+
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 10);
+        backups.back_up(zed_config_dir().child("keymap.json").path())?;
+        backups.back_up(zed_config_dir().child("tasks.json").path())?;
+
+        let snapshots = backups.snapshots()?;
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].file_names, ["keymap.json", "tasks.json"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_old_snapshots_are_pruned() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+
+        for _ in 0..3 {
+            let mut backups = BackupStore::new(&zed_paths::data_dir(), 2);
+            backups.back_up(zed_config_dir().child("keymap.json").path())?;
+        }
+
+        assert_eq!(
+            BackupStore::new(&zed_paths::data_dir(), 2)
+                .snapshots()?
+                .len(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_backs_up_overwritten_files() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[1]")?;
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 10);
+        backups.back_up(zed_config_dir().child("keymap.json").path())?;
+        let snapshot_name = backups.snapshots()?[0].name.clone();
+
+        zed_config_dir().child("keymap.json").write_str("[2]")?;
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 10);
+        assert_eq!(
+            backups.restore(&snapshot_name, zed_config_dir().path())?,
+            ["keymap.json"]
+        );
+
+        zed_config_dir().child("keymap.json").assert("[1]");
+        let snapshots = backups.snapshots()?;
+        assert_eq!(snapshots.len(), 2);
+        assert_ne!(snapshots[0].name, snapshot_name);
+        assert_eq!(
+            fs::read_to_string(
+                zed_paths::data_dir()
+                    .join(BACKUPS_DIR)
+                    .join(&snapshots[0].name)
+                    .join("keymap.json")
+            )?,
+            "[2]"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restoring_unknown_snapshot_fails() {
+        let mut backups = BackupStore::new(&zed_paths::data_dir(), 10);
+
+        assert_eq!(
+            backups
+                .restore("2020-01-01_00-00-00.000", zed_config_dir().path())
+                .unwrap_err()
+                .to_string(),
+            "Backup 2020-01-01_00-00-00.000 not found"
+        );
+    }
+}
@@ -1,13 +1,20 @@
-use std::fmt::Debug;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+};
 
 use anyhow::Result;
+pub use archive_client::*;
 use async_trait::async_trait;
 pub use github_client::*;
+pub use remote_files::MASKED_GITHUB_TOKEN;
 use thiserror::Error;
 
 use crate::sync::{Error, LocalFileData};
 
+mod archive_client;
 mod github_client;
+mod remote_files;
 
 pub type FileResult = Result<(String, String), FileError>;
 
@@ -27,6 +34,49 @@ pub trait Client: Send + Sync {
         reason = "This trait is intended to be used by zed-settings-sync crate only"
     )]
     async fn load_files(&self) -> Result<Box<dyn Iterator<Item = FileResult>>, Error>;
+
+    /// Identifier of the latest revision of the remote storage, if it keeps revisions
+    #[allow(clippy::missing_errors_doc)]
+    #[allow(
+        async_fn_in_trait,
+        reason = "This trait is intended to be used by zed-settings-sync crate only"
+    )]
+    async fn remote_revision(&self) -> Result<Option<String>, Error>;
+
+    /// Identifiers of the stored revisions, oldest first; empty if the storage doesn't keep revisions
+    #[allow(clippy::missing_errors_doc)]
+    #[allow(
+        async_fn_in_trait,
+        reason = "This trait is intended to be used by zed-settings-sync crate only"
+    )]
+    async fn revisions(&self) -> Result<Vec<String>, Error>;
+
+    /// Stored files by name as they are, not prepared for writing locally; of the latest revision if not given
+    #[allow(clippy::missing_errors_doc)]
+    #[allow(
+        async_fn_in_trait,
+        reason = "This trait is intended to be used by zed-settings-sync crate only"
+    )]
+    async fn load_raw_files(
+        &self,
+        revision: Option<String>,
+    ) -> Result<BTreeMap<String, String>, Error>;
+
+    /// Stores the files as they are, leaving other stored files intact
+    #[allow(clippy::missing_errors_doc)]
+    #[allow(
+        async_fn_in_trait,
+        reason = "This trait is intended to be used by zed-settings-sync crate only"
+    )]
+    async fn store_raw_files(&self, files: BTreeMap<String, String>) -> Result<(), Error>;
+
+    /// Deletes the stored files with the given names
+    #[allow(clippy::missing_errors_doc)]
+    #[allow(
+        async_fn_in_trait,
+        reason = "This trait is intended to be used by zed-settings-sync crate only"
+    )]
+    async fn delete_raw_files(&self, file_names: BTreeSet<String>) -> Result<(), Error>;
 }
 
 #[derive(Error, Debug)]
@@ -43,4 +93,14 @@ impl FileError {
             error,
         }
     }
+
+    #[must_use]
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    #[must_use]
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
 }
@@ -0,0 +1,209 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    path::Path,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::sync::{
+    ArchiveManifest, Client, Error, FileError, FileResult, LocalFileData, SyncOptions,
+    TemplateVariables, client::remote_files::RemoteFileIterator, file_digest, read_archive,
+};
+
+/// Read-only client loading files from an archive written by [`crate::sync::export_archive`]
+#[derive(Debug)]
+pub struct ArchiveClient {
+    manifest: ArchiveManifest,
+    files: BTreeMap<String, String>,
+    github_token: Option<String>,
+    options: SyncOptions,
+}
+
+impl ArchiveClient {
+    /// Opens the archive, the masked token in the archived settings file is replaced with `github_token` if given
+    #[allow(clippy::missing_errors_doc)]
+    pub fn open(path: &Path, github_token: Option<String>, options: SyncOptions) -> Result<Self> {
+        let (manifest, files) = read_archive(path)?;
+
+        Ok(Self {
+            manifest,
+            files,
+            github_token,
+            options,
+        })
+    }
+
+    #[must_use]
+    pub fn manifest(&self) -> &ArchiveManifest {
+        &self.manifest
+    }
+
+    fn verify(&self, file_name: &str, content: &str) -> Result<(), Error> {
+        // the name is used as a path in the Zed config dir
+        if Path::new(file_name).file_name() != Some(OsStr::new(file_name)) {
+            return Err(Error::Archive(format!("Invalid file name {file_name}")));
+        }
+
+        match self.manifest.files.get(file_name) {
+            Some(digest) if *digest == file_digest(content) => Ok(()),
+            Some(_) => Err(Error::Archive(
+                "Content doesn't match the hash from the manifest".to_string(),
+            )),
+            None => Err(Error::Archive(
+                "File is not listed in the manifest".to_string(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Client for ArchiveClient {
+    async fn sync_file(&self, data: LocalFileData) -> Result<(), FileError> {
+        Err(FileError::from_error(
+            data.filename,
+            Error::Archive("Archive is read-only".to_string()),
+        ))
+    }
+
+    async fn load_files(&self) -> Result<Box<dyn Iterator<Item = FileResult>>, Error> {
+        let mut verified = BTreeMap::new();
+        let mut errors = Vec::new();
+        for (file_name, content) in &self.files {
+            match self.verify(file_name, content) {
+                Ok(()) => {
+                    verified.insert(file_name.clone(), content.clone());
+                }
+                Err(error) => errors.push(Err(FileError::from_error(file_name, error))),
+            }
+        }
+
+        let files = RemoteFileIterator::new(
+            verified,
+            self.github_token.clone(),
+            &self.options,
+            TemplateVariables::local(),
+        );
+
+        Ok(Box::new(files.chain(errors)))
+    }
+
+    async fn remote_revision(&self) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    async fn revisions(&self) -> Result<Vec<String>, Error> {
+        Ok(Vec::new())
+    }
+
+    async fn load_raw_files(
+        &self,
+        revision: Option<String>,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        if let Some(revision) = revision {
+            return Err(Error::Archive(format!(
+                "Archive has no revision {revision}"
+            )));
+        }
+
+        for (file_name, content) in &self.files {
+            self.verify(file_name, content)
+                .map_err(|error| Error::Archive(format!("{file_name}: {error}")))?;
+        }
+
+        Ok(self.files.clone())
+    }
+
+    async fn store_raw_files(&self, _files: BTreeMap<String, String>) -> Result<(), Error> {
+        Err(Error::Archive("Archive is read-only".to_string()))
+    }
+
+    async fn delete_raw_files(&self, _file_names: BTreeSet<String>) -> Result<(), Error> {
+        Err(Error::Archive("Archive is read-only".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::fs::File;
+
+    use assert_fs::TempDir;
+    use flate2::{Compression, write::GzEncoder};
+    use tar::{Builder, Header};
+    use zed_extension_api::serde_json::to_string;
+
+    use super::*;
+    use crate::sync::export_archive;
+
+    #[tokio::test]
+    async fn test_exported_files_are_loaded_with_token_unmasked() -> Result<()> {
+        let dir = TempDir::new()?;
+        let archive_path = dir.path().join("settings.tar.gz");
+        let settings = LocalFileData::new(
+            dir.path().join("settings.json"),
+            r#"{ "lsp": { "settings-sync": { "initialization_options": { "github_token": "gho_secret" } } } }"#
+                .to_string(),
+        )?;
+        export_archive(
+            &archive_path,
+            &[settings],
+            &BTreeMap::new(),
+            &SyncOptions::default(),
+            "1.2.3",
+        )?;
+
+        let client = ArchiveClient::open(
+            &archive_path,
+            Some("gho_local".to_string()),
+            SyncOptions::default(),
+        )?;
+        let files: Vec<_> = client.load_files().await?.collect();
+
+        assert_eq!(files.len(), 1);
+        let (file_name, content) = files[0].as_ref().unwrap();
+        assert_eq!(file_name, "settings.json");
+        assert!(content.contains("gho_local"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tampered_files_are_reported() -> Result<()> {
+        let dir = TempDir::new()?;
+        let archive_path = dir.path().join("settings.tar.gz");
+        let manifest = ArchiveManifest {
+            format_version: 1,
+            created_by: "1.2.3".to_string(),
+            created_at: chrono::Utc::now(),
+            files: [("keymap.json".to_string(), file_digest("[]"))].into(),
+        };
+
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(&archive_path)?,
+            Compression::default(),
+        ));
+        for (name, content) in [
+            ("manifest.json", to_string(&manifest)?),
+            ("keymap.json", "[{}]".to_string()),
+        ] {
+            let mut header = Header::new_gnu();
+            header.set_size(u64::try_from(content.len())?);
+            header.set_cksum();
+            builder.append_data(&mut header, name, content.as_bytes())?;
+        }
+        builder.into_inner()?.finish()?;
+
+        let client = ArchiveClient::open(&archive_path, None, SyncOptions::default())?;
+        let files: Vec<_> = client.load_files().await?.collect();
+
+        assert_eq!(
+            files[0].as_ref().unwrap_err().to_string(),
+            "Error syncing file keymap.json: Archive error: Content doesn't match the hash from the manifest"
+        );
+
+        Ok(())
+    }
+}
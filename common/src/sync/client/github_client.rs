@@ -1,112 +1,192 @@
-use std::collections::{BTreeMap, btree_map::IntoIter};
+use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use jsonc_parser::{ParseOptions, cst::CstRootNode};
-use octocrab::models::gists::GistFile;
 use paths as zed_paths;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
+use zed_extension_api::serde_json::{Map, Value, json};
 
-use crate::{
-    ZED_CONFIG_FILE_NAME,
-    sync::{Client, Error, FileError, FileResult, LocalFileData},
+use crate::sync::{
+    CURRENT_OS, Client, Error, FileError, FileResult, LocalFileData, SyncDirection, SyncEvent,
+    SyncEventLog, SyncOptions, SyncStateStore, TemplateVariables,
+    client::remote_files::{RemoteFileIterator, transform_file_body_for_remote},
+    has_os_sections, restore_foreign_os_sections,
 };
 
+const GIST_DESCRIPTION: &str = "Zed settings";
+const GIST_README_FILE_NAME: &str = "README.md";
+const GIST_README_CONTENT: &str = "Zed settings files synced by [zed-settings-sync](https://github.com/vittorius/zed-settings-sync).\n";
+
+/// What the token can do with the configured gist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GistAccess {
+    Writable,
+    /// The gist belongs to another user
+    ReadOnly {
+        owner: String,
+    },
+    NotFound,
+}
+
 #[derive(Debug)]
 pub struct GithubClient {
     octocrab: octocrab::Octocrab,
     gist_id: String,
     github_token: String,
+    options: SyncOptions,
+    template_variables: TemplateVariables,
+    state_store: SyncStateStore,
+    event_log: SyncEventLog,
 }
 
-struct GithubFileIterator {
-    inner: IntoIter<String, GistFile>,
-    github_token: String,
-}
+#[async_trait]
+impl Client for GithubClient {
+    #[instrument(skip_all)]
+    async fn sync_file(&self, data: LocalFileData) -> Result<(), FileError> {
+        info!("Syncing file: {}", data.path.display());
 
-impl GithubFileIterator {
-    #[must_use]
-    pub fn new(files: BTreeMap<String, GistFile>, github_token: String) -> Self {
-        Self {
-            inner: files.into_iter(),
-            github_token,
+        let (result, remote_revision) = match self.push_file(&data).await {
+            Ok(remote_revision) => (Ok(()), remote_revision),
+            Err(err) => (Err(err), None),
+        };
+        let event = SyncEvent::new(
+            SyncDirection::Push,
+            &data.filename,
+            &data.body,
+            remote_revision,
+            result.as_ref().err(),
+        );
+        if let Err(err) = self.event_log.append(&event) {
+            warn!("Failed to log sync event of {}: {err}", data.filename);
         }
-    }
+        result.map_err(|err| FileError::from_error(&data.filename, err))?;
 
-    fn transform_file_body(&self, body: &str, is_settings_file: bool) -> Result<String, Error> {
-        let root =
-            CstRootNode::parse(body, &ParseOptions::default()).map_err(Error::InvalidJson)?;
+        info!("File synced: {}", data.path.display());
 
-        if is_settings_file {
-            self.unmask_auth_token(&root)?;
+        if let Err(err) = self.state_store.record_synced(&data.filename, &data.body) {
+            warn!("Failed to record sync state of {}: {err}", data.filename);
         }
 
-        Ok(root.to_string())
+        Ok(())
     }
 
-    fn unmask_auth_token(&self, root: &CstRootNode) -> Result<(), Error> {
-        set_github_token_config_value(root, self.github_token.clone())
+    #[instrument(skip_all)]
+    async fn load_files(&self) -> Result<Box<dyn Iterator<Item = FileResult>>, Error> {
+        let files = self
+            .octocrab
+            .gists()
+            .get(&self.gist_id)
+            .await?
+            .files
+            .into_iter()
+            .filter_map(|(file_name, file)| Some((file_name, file.content?)))
+            .collect();
+
+        Ok(Box::new(RemoteFileIterator::new(
+            files,
+            Some(self.github_token.clone()),
+            &self.options,
+            self.template_variables.clone(),
+        )))
     }
-}
 
-impl Iterator for GithubFileIterator {
-    type Item = FileResult;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.inner.find(|(file_name, file)| {
-            file_name.to_lowercase().ends_with(".json") && file.content.is_some()
-        }) {
-            Some((file_name, file)) => match self.transform_file_body(
-                #[allow(clippy::expect_used)]
-                &file
-                    .content
-                    .expect("File content presence was already verified"),
-                file_name == *ZED_CONFIG_FILE_NAME,
-            ) {
-                Ok(content) => Some(Ok((file_name, content))),
-                Err(error) => Some(Err(FileError { file_name, error })),
-            },
-            None => None,
-        }
+    #[instrument(skip_all)]
+    async fn remote_revision(&self) -> Result<Option<String>, Error> {
+        // gist history isn't exposed by octocrab models, so using the raw response
+        let gist: Value = self
+            .octocrab
+            .get(format!("/gists/{}", self.gist_id), None::<&()>)
+            .await?;
+
+        Ok(gist
+            .pointer("/history/0/version")
+            .and_then(Value::as_str)
+            .map(str::to_string))
     }
-}
 
-#[async_trait]
-impl Client for GithubClient {
     #[instrument(skip_all)]
-    async fn sync_file(&self, data: LocalFileData) -> Result<(), FileError> {
-        info!("Syncing file: {}", data.path.display());
+    async fn revisions(&self) -> Result<Vec<String>, Error> {
+        let gist: Value = self
+            .octocrab
+            .get(format!("/gists/{}", self.gist_id), None::<&()>)
+            .await?;
 
-        let body =
-            Self::transform_file_body_on_sync(&data.body, &data.path == zed_paths::settings_file())
-                .map_err(|err| FileError::from_error(&data.filename, err))?;
+        // Github lists the history newest first
+        Ok(gist
+            .get("history")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .rev()
+            .filter_map(|entry| entry.get("version").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect())
+    }
 
-        self.octocrab
-            .gists()
-            .update(&self.gist_id)
-            .file(&data.filename)
-            .with_content(&body)
-            .send()
-            .await
-            .map_err(|err| FileError::from_error(&data.filename, err.into()))?;
+    #[instrument(skip_all)]
+    async fn load_raw_files(
+        &self,
+        revision: Option<String>,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        let route = match revision {
+            Some(revision) => format!("/gists/{}/{revision}", self.gist_id),
+            None => format!("/gists/{}", self.gist_id),
+        };
+        let gist: Value = self.octocrab.get(route, None::<&()>).await?;
 
-        info!("File synced: {}", data.path.display());
+        Ok(gist
+            .get("files")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(file_name, file)| {
+                let content = file.get("content")?.as_str()?;
+                Some((file_name.clone(), content.to_string()))
+            })
+            .collect())
+    }
+
+    #[instrument(skip_all)]
+    async fn store_raw_files(&self, files: BTreeMap<String, String>) -> Result<(), Error> {
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut update = self.octocrab.gists().update(&self.gist_id);
+        for (file_name, content) in &files {
+            update = update.file(file_name).with_content(content);
+        }
+        update.send().await?;
 
         Ok(())
     }
 
     #[instrument(skip_all)]
-    async fn load_files(&self) -> Result<Box<dyn Iterator<Item = FileResult>>, Error> {
-        Ok(Box::new(GithubFileIterator::new(
-            self.octocrab.gists().get(&self.gist_id).await?.files,
-            self.github_token.clone(),
-        )))
+    async fn delete_raw_files(&self, file_names: BTreeSet<String>) -> Result<(), Error> {
+        if file_names.is_empty() {
+            return Ok(());
+        }
+
+        // Github deletes the files set to null, which octocrab's update builder can't express
+        let files: Map<String, Value> = file_names
+            .into_iter()
+            .map(|file_name| (file_name, Value::Null))
+            .collect();
+        let _: Value = self
+            .octocrab
+            .patch(
+                format!("/gists/{}", self.gist_id),
+                Some(&json!({ "files": files })),
+            )
+            .await?;
+
+        Ok(())
     }
 }
 
 impl GithubClient {
     #[allow(clippy::missing_errors_doc)]
-    pub fn new(gist_id: String, github_token: String) -> Result<Self> {
+    pub fn new(gist_id: String, github_token: String, options: SyncOptions) -> Result<Self> {
         let client = octocrab::Octocrab::builder()
             .personal_token(github_token.clone())
             .build()
@@ -116,63 +196,143 @@ impl GithubClient {
             octocrab: client,
             gist_id,
             github_token,
+            options,
+            template_variables: TemplateVariables::local(),
+            state_store: SyncStateStore::new(zed_paths::data_dir()),
+            event_log: SyncEventLog::new(zed_paths::data_dir()),
         })
     }
 
-    fn transform_file_body_on_sync(body: &str, is_settings_file: bool) -> Result<String, Error> {
-        let root =
-            CstRootNode::parse(body, &ParseOptions::default()).map_err(Error::InvalidJson)?;
+    /// Creates a new secret gist for settings files and returns its ID
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn create_gist(github_token: String) -> Result<String> {
+        let client = octocrab::Octocrab::builder()
+            .personal_token(github_token)
+            .build()
+            .with_context(|| "Failed to build the Github client")?;
 
-        if is_settings_file {
-            Self::mask_auth_token(&root)?;
-        }
+        // a gist can't be created without files, settings files are synced to it afterward
+        let gist = client
+            .gists()
+            .create()
+            .description(GIST_DESCRIPTION)
+            .public(false)
+            .file(GIST_README_FILE_NAME, GIST_README_CONTENT)
+            .send()
+            .await
+            .map_err(Error::from)?;
 
-        Ok(root.to_string())
+        Ok(gist.id)
     }
 
-    fn mask_auth_token(root: &CstRootNode) -> Result<(), Error> {
-        set_github_token_config_value(root, "[masked]".into())
+    /// OAuth scopes of the token, `None` if Github doesn't report them, as for fine-grained tokens
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn token_scopes(&self) -> Result<Option<Vec<String>>, Error> {
+        let response = octocrab::map_github_error(self.octocrab._get("/user").await?).await?;
+
+        Ok(response
+            .headers()
+            .get("x-oauth-scopes")
+            .and_then(|scopes| scopes.to_str().ok())
+            .map(|scopes| {
+                scopes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|scope| !scope.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }))
     }
-}
 
-fn set_github_token_config_value(root: &CstRootNode, value: String) -> Result<(), Error> {
-    let root_obj = root.object_value_or_set();
-    root_obj
-        .get("lsp")
-        .ok_or(Error::InvalidConfig(r#"Missing "lsp" key"#.to_string()))?
-        .object_value()
-        .ok_or(Error::InvalidConfig(
-            r#"Missing "lsp" configuration object"#.to_string(),
-        ))?
-        .get("settings-sync")
-        .ok_or(Error::InvalidConfig(
-            r#"Missing "settings-sync" key"#.to_string(),
-        ))?
-        .object_value()
-        .ok_or(Error::InvalidConfig(
-            r#"Missing "settings-sync" configuration object"#.to_string(),
-        ))?
-        .get("initialization_options")
-        .ok_or(Error::InvalidConfig(
-            r#"Missing "initialization_options" key"#.to_string(),
-        ))?
-        .object_value()
-        .ok_or(Error::InvalidConfig(
-            r#"Missing "initialization_options" configuration object"#.to_string(),
-        ))?
-        .get("github_token")
-        .ok_or(Error::InvalidConfig("Missing github_token".to_string()))?
-        .set_value(value.into());
-
-    Ok(())
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn gist_access(&self) -> Result<GistAccess, Error> {
+        let gist: Value = match self
+            .octocrab
+            .get(format!("/gists/{}", self.gist_id), None::<&()>)
+            .await
+        {
+            Ok(gist) => gist,
+            Err(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 404 => {
+                return Ok(GistAccess::NotFound);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let user = self.octocrab.current().user().await?;
+
+        // only the owner can update a gist
+        match gist.pointer("/owner/login").and_then(Value::as_str) {
+            Some(owner) if owner != user.login => Ok(GistAccess::ReadOnly {
+                owner: owner.to_string(),
+            }),
+            _ => Ok(GistAccess::Writable),
+        }
+    }
+
+    /// Returns the revision created by the update
+    async fn push_file(&self, data: &LocalFileData) -> Result<Option<String>, Error> {
+        let local_body = self.with_foreign_os_sections(data).await?;
+        let (body, profile_overlay) = transform_file_body_for_remote(
+            &local_body,
+            &data.path == zed_paths::settings_file(),
+            &self.options,
+            &self.template_variables,
+        )?;
+
+        let mut files = Map::new();
+        files.insert(data.filename.clone(), json!({ "content": body }));
+        if let Some(overlay) = profile_overlay {
+            files.insert(
+                self.options.profile.overlay_file_name(),
+                json!({ "content": overlay }),
+            );
+        }
+
+        // the update response model of octocrab doesn't expose the history, so using the raw response
+        let gist: Value = self
+            .octocrab
+            .patch(
+                format!("/gists/{}", self.gist_id),
+                Some(&json!({ "files": files })),
+            )
+            .await?;
+
+        Ok(gist
+            .pointer("/history/0/version")
+            .and_then(Value::as_str)
+            .map(str::to_string))
+    }
+
+    // sections for other OSes are emptied when a file is materialized locally, so they are taken from the remote copy
+    async fn with_foreign_os_sections(&self, data: &LocalFileData) -> Result<String, Error> {
+        if !has_os_sections(&data.body) {
+            return Ok(data.body.clone());
+        }
+
+        let remote_body = self
+            .octocrab
+            .gists()
+            .get(&self.gist_id)
+            .await?
+            .files
+            .remove(&data.filename)
+            .and_then(|file| file.content);
+
+        match remote_body {
+            Some(remote_body) => restore_foreign_os_sections(&data.body, &remote_body, CURRENT_OS),
+            None => Ok(data.body.clone()),
+        }
+    }
 }
 
-// currently, GithubClient is the only implementation of Client, so mocking it directly
+// mocking GithubClient itself rather than only Client, since callers also use its inherent `new`, `create_gist`, `token_scopes` and `gist_access`
 #[cfg(feature = "test-support")]
 mockall::mock! {
     pub GithubClient {
         pub fn id(&self) -> String; // for identity tracking in tests
-        pub fn new(gist_id: String, github_token: String) -> Result<Self>;
+        pub fn new(gist_id: String, github_token: String, options: SyncOptions) -> Result<Self>;
+        pub async fn create_gist(github_token: String) -> Result<String>;
+        pub async fn token_scopes(&self) -> Result<Option<Vec<String>>, Error>;
+        pub async fn gist_access(&self) -> Result<GistAccess, Error>;
     }
 
     #[async_trait]
@@ -182,5 +342,16 @@ mockall::mock! {
 
         async fn load_files(&self)
             -> Result<Box<dyn Iterator<Item = FileResult>>, Error>;
+
+        async fn remote_revision(&self) -> Result<Option<String>, Error>;
+
+        async fn revisions(&self) -> Result<Vec<String>, Error>;
+
+        async fn load_raw_files(&self, revision: Option<String>)
+            -> Result<BTreeMap<String, String>, Error>;
+
+        async fn store_raw_files(&self, files: BTreeMap<String, String>) -> Result<(), Error>;
+
+        async fn delete_raw_files(&self, file_names: BTreeSet<String>) -> Result<(), Error>;
     }
 }
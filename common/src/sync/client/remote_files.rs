@@ -0,0 +1,153 @@
+use std::{
+    collections::{BTreeMap, btree_map::IntoIter},
+    path::Path,
+};
+
+use jsonc_parser::{ParseOptions, cst::CstRootNode};
+
+use crate::{
+    ZED_CONFIG_FILE_NAME,
+    sync::{Error, FileError, FileFilter, FileResult, Profile, SyncOptions, TemplateVariables},
+};
+
+/// Stored in place of the Github token in the synced settings file
+pub const MASKED_GITHUB_TOKEN: &str = "[masked]";
+
+/// Turns the content of remote files into the one to be written locally, skipping files that aren't synced
+pub(crate) struct RemoteFileIterator {
+    inner: IntoIter<String, String>,
+    // the masked token is kept as is if not given
+    github_token: Option<String>,
+    file_filter: FileFilter,
+    profile_overlay: Option<String>,
+    template_variables: TemplateVariables,
+}
+
+impl RemoteFileIterator {
+    #[must_use]
+    pub fn new(
+        mut files: BTreeMap<String, String>,
+        github_token: Option<String>,
+        options: &SyncOptions,
+        template_variables: TemplateVariables,
+    ) -> Self {
+        let profile_overlay = files.remove(&options.profile.overlay_file_name());
+
+        Self {
+            inner: files.into_iter(),
+            github_token,
+            file_filter: options.file_filter.clone(),
+            profile_overlay,
+            template_variables,
+        }
+    }
+
+    fn transform_file_body(&self, body: &str, is_settings_file: bool) -> Result<String, Error> {
+        let mut root =
+            CstRootNode::parse(body, &ParseOptions::default()).map_err(Error::InvalidJson)?;
+
+        if is_settings_file {
+            if let Some(github_token) = &self.github_token {
+                set_github_token_config_value(&root, github_token.clone())?;
+            }
+
+            if let Some(overlay) = &self.profile_overlay {
+                root = Profile::merge(&root, overlay)?;
+            }
+        }
+
+        self.template_variables.expand_values(&root);
+
+        Ok(root.to_string())
+    }
+}
+
+impl Iterator for RemoteFileIterator {
+    type Item = FileResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (file_name, body) = self.inner.find(|(file_name, _)| {
+            file_name.to_lowercase().ends_with(".json")
+                && !Profile::is_overlay_file_name(file_name)
+                && self.file_filter.matches(Path::new(file_name))
+        })?;
+
+        match self.transform_file_body(&body, file_name == *ZED_CONFIG_FILE_NAME) {
+            Ok(content) => Some(Ok((file_name, content))),
+            Err(error) => Some(Err(FileError { file_name, error })),
+        }
+    }
+}
+
+/// Returns the file body to be stored remotely and, for a settings file, its per-machine profile overlay
+pub(crate) fn transform_file_body_for_remote(
+    body: &str,
+    is_settings_file: bool,
+    options: &SyncOptions,
+    template_variables: &TemplateVariables,
+) -> Result<(String, Option<String>), Error> {
+    let root = CstRootNode::parse(body, &ParseOptions::default()).map_err(Error::InvalidJson)?;
+    let mut profile_overlay = None;
+
+    template_variables.templatize_values(&root);
+
+    if is_settings_file {
+        // the token may come from elsewhere, e.g. an environment variable, leaving nothing to mask
+        if has_github_token_config_value(&root) {
+            set_github_token_config_value(&root, MASKED_GITHUB_TOKEN.into())?;
+        }
+
+        if options.profile.has_machine_local_keys() {
+            profile_overlay = Some(options.profile.split(&root)?);
+        }
+    }
+
+    Ok((root.to_string(), profile_overlay))
+}
+
+fn has_github_token_config_value(root: &CstRootNode) -> bool {
+    root.object_value()
+        .and_then(|root_obj| {
+            root_obj
+                .get("lsp")?
+                .object_value()?
+                .get("settings-sync")?
+                .object_value()?
+                .get("initialization_options")?
+                .object_value()?
+                .get("github_token")
+        })
+        .is_some()
+}
+
+fn set_github_token_config_value(root: &CstRootNode, value: String) -> Result<(), Error> {
+    let root_obj = root.object_value_or_set();
+    root_obj
+        .get("lsp")
+        .ok_or(Error::InvalidConfig(r#"Missing "lsp" key"#.to_string()))?
+        .object_value()
+        .ok_or(Error::InvalidConfig(
+            r#"Missing "lsp" configuration object"#.to_string(),
+        ))?
+        .get("settings-sync")
+        .ok_or(Error::InvalidConfig(
+            r#"Missing "settings-sync" key"#.to_string(),
+        ))?
+        .object_value()
+        .ok_or(Error::InvalidConfig(
+            r#"Missing "settings-sync" configuration object"#.to_string(),
+        ))?
+        .get("initialization_options")
+        .ok_or(Error::InvalidConfig(
+            r#"Missing "initialization_options" key"#.to_string(),
+        ))?
+        .object_value()
+        .ok_or(Error::InvalidConfig(
+            r#"Missing "initialization_options" configuration object"#.to_string(),
+        ))?
+        .get("github_token")
+        .ok_or(Error::InvalidConfig("Missing github_token".to_string()))?
+        .set_value(value.into());
+
+    Ok(())
+}
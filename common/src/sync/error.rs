@@ -14,6 +14,8 @@ pub enum Error {
     Internal(Box<dyn std::error::Error + Send + Sync>),
     #[error("Unhandled internal error from underlying client library: {0}")]
     UnhandledInternal(String),
+    #[error("Archive error: {0}")]
+    Archive(String),
 }
 
 impl From<OctocrabError> for Error {
@@ -41,3 +43,18 @@ impl From<OctocrabError> for Error {
         }
     }
 }
+
+impl Error {
+    /// Stable name of the error variant, for machine-readable output
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::InvalidJson(_) => "invalid_json",
+            Error::InvalidConfig(_) => "invalid_config",
+            Error::Github(_) => "github",
+            Error::Internal(_) => "internal",
+            Error::UnhandledInternal(_) => "unhandled_internal",
+            Error::Archive(_) => "archive",
+        }
+    }
+}
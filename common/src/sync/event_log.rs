@@ -0,0 +1,304 @@
+use std::{
+    ffi::OsString,
+    fmt::{self, Display},
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use zed_extension_api::serde_json::{from_str, to_string};
+
+use crate::sync::{Error, content_hash};
+
+const EVENT_LOG_DIR_NAME: &str = "settings-sync";
+const EVENT_LOG_FILE_NAME: &str = "sync-log.jsonl";
+// the log is moved aside once over this size, so at most twice as much is kept
+const MAX_EVENT_LOG_SIZE: u64 = 1024 * 1024;
+
+// keeps lines of concurrent syncs within a process from interleaving
+static EVENT_LOG_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    /// A local file was stored in the gist
+    Push,
+    /// A remote file was written locally
+    Load,
+}
+
+impl Display for SyncDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SyncDirection::Push => "push",
+            SyncDirection::Load => "load",
+        };
+
+        // respecting width and alignment of the log columns
+        f.pad(text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncResult {
+    Ok,
+    Error,
+}
+
+/// Entry of the sync event log, one JSON line per synced file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncEvent {
+    pub timestamp: DateTime<Utc>,
+    pub direction: SyncDirection,
+    pub file: String,
+    /// Content size in bytes
+    pub size: usize,
+    /// See [`content_hash`]
+    pub hash: String,
+    pub remote_revision: Option<String>,
+    pub result: SyncResult,
+    /// See [`Error::kind`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<String>,
+}
+
+impl SyncEvent {
+    #[must_use]
+    pub fn new(
+        direction: SyncDirection,
+        file: &str,
+        content: &str,
+        remote_revision: Option<String>,
+        error: Option<&Error>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            direction,
+            file: file.to_string(),
+            size: content.len(),
+            hash: content_hash(content),
+            remote_revision,
+            result: if error.is_some() {
+                SyncResult::Error
+            } else {
+                SyncResult::Ok
+            },
+            error_kind: error.map(|error| error.kind().to_string()),
+        }
+    }
+}
+
+/// Which logged events to return, all of them by default
+#[derive(Debug, Clone, Default)]
+pub struct SyncEventFilter {
+    pub file: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl SyncEventFilter {
+    #[must_use]
+    pub fn matches(&self, event: &SyncEvent) -> bool {
+        self.file.as_ref().is_none_or(|file| *file == event.file)
+            && self.since.is_none_or(|since| event.timestamp >= since)
+            && self.until.is_none_or(|until| event.timestamp <= until)
+    }
+}
+
+/// Append-only JSON lines log of sync events, kept next to the sync state.
+/// Once it grows over the size limit, it replaces the previous one moved to `<name>.1`
+#[derive(Debug, Clone)]
+pub struct SyncEventLog {
+    path: PathBuf,
+    max_size: u64,
+}
+
+impl SyncEventLog {
+    #[must_use]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(EVENT_LOG_DIR_NAME).join(EVENT_LOG_FILE_NAME),
+            max_size: MAX_EVENT_LOG_SIZE,
+        }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn append(&self, event: &SyncEvent) -> Result<()> {
+        let _guard = EVENT_LOG_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(log_dir) = self.path.parent() {
+            fs::create_dir_all(log_dir)?;
+        }
+        self.rotate_if_full()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", to_string(event)?)?;
+
+        Ok(())
+    }
+
+    /// Logged events matching the filter, oldest first
+    #[allow(clippy::missing_errors_doc)]
+    pub fn query(&self, filter: &SyncEventFilter) -> Result<Vec<SyncEvent>> {
+        let mut events = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // a line may be cut short if the process was killed while writing it
+                match from_str::<SyncEvent>(&line) {
+                    Ok(event) if filter.matches(&event) => events.push(event),
+                    Ok(_) => {}
+                    Err(err) => warn!(
+                        "Skipping line {} of sync event log {}: {err}",
+                        index + 1,
+                        path.display()
+                    ),
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    fn rotate_if_full(&self) -> Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        if size < self.max_size {
+            return Ok(());
+        }
+
+        // another process may have rotated it already
+        match fs::rename(&self.path, self.rotated_path()) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let mut path = OsString::from(&self.path);
+        path.push(".1");
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_appended_events_are_queried_by_file() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let log = SyncEventLog::new(data_dir.path());
+
+        log.append(&SyncEvent::new(
+            SyncDirection::Push,
+            "settings.json",
+            "{}",
+            Some("abc123".to_string()),
+            None,
+        ))?;
+        log.append(&SyncEvent::new(
+            SyncDirection::Load,
+            "keymap.json",
+            "[]",
+            None,
+            Some(&Error::InvalidConfig("broken".to_string())),
+        ))?;
+
+        let events = log.query(&SyncEventFilter {
+            file: Some("keymap.json".to_string()),
+            ..SyncEventFilter::default()
+        })?;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].direction, SyncDirection::Load);
+        assert_eq!(events[0].size, 2);
+        assert_eq!(events[0].result, SyncResult::Error);
+        assert_eq!(events[0].error_kind.as_deref(), Some("invalid_config"));
+        assert_eq!(log.query(&SyncEventFilter::default())?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_events_are_filtered_by_time_range() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let log = SyncEventLog::new(data_dir.path());
+        let event = SyncEvent::new(SyncDirection::Push, "tasks.json", "[]", None, None);
+        log.append(&event)?;
+
+        let before = event.timestamp - chrono::Duration::minutes(1);
+        let after = event.timestamp + chrono::Duration::minutes(1);
+
+        assert_eq!(
+            log.query(&SyncEventFilter {
+                since: Some(before),
+                until: Some(after),
+                ..SyncEventFilter::default()
+            })?,
+            [event]
+        );
+        assert!(
+            log.query(&SyncEventFilter {
+                since: Some(after),
+                ..SyncEventFilter::default()
+            })?
+            .is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_log_is_rotated_and_queried_with_the_previous_one() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let log = SyncEventLog {
+            max_size: 1,
+            ..SyncEventLog::new(data_dir.path())
+        };
+
+        for file in ["settings.json", "keymap.json", "tasks.json"] {
+            log.append(&SyncEvent::new(SyncDirection::Push, file, "{}", None, None))?;
+        }
+
+        // the oldest event is dropped with the previous rotated log
+        let files: Vec<String> = log
+            .query(&SyncEventFilter::default())?
+            .into_iter()
+            .map(|event| event.file)
+            .collect();
+        assert_eq!(files, ["keymap.json", "tasks.json"]);
+        assert!(log.rotated_path().exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Include/exclude glob rules deciding which Zed config files take part in syncing.
+///
+/// Patterns are matched against a file path relative to the Zed config dir (e.g. `debug.json`).
+/// An empty include list means "include everything".
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl FileFilter {
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: build_glob_set(include)?,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    #[must_use]
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        (self.include.is_empty() || self.include.is_match(relative_path))
+            && !self.exclude.is_match(relative_path)
+    }
+}
+
+impl Default for FileFilter {
+    fn default() -> Self {
+        Self {
+            include: GlobSet::empty(),
+            exclude: GlobSet::empty(),
+        }
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder
+            .add(Glob::new(pattern).with_context(|| format!("Invalid glob pattern: {pattern}"))?);
+    }
+
+    builder
+        .build()
+        .with_context(|| "Failed to build glob set from sync rules")
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_default_filter_matches_everything() {
+        let filter = FileFilter::default();
+
+        assert!(filter.matches(Path::new("settings.json")));
+        assert!(filter.matches(Path::new("debug.json")));
+    }
+
+    #[test]
+    fn test_include_rules_limit_matched_files() -> Result<()> {
+        let filter = FileFilter::new(&strings(&["settings.json", "key*.json"]), &[])?;
+
+        assert!(filter.matches(Path::new("settings.json")));
+        assert!(filter.matches(Path::new("keymap.json")));
+        assert!(!filter.matches(Path::new("tasks.json")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_rules_take_precedence_over_include_rules() -> Result<()> {
+        let filter = FileFilter::new(&strings(&["*.json"]), &strings(&["debug.json"]))?;
+
+        assert!(filter.matches(Path::new("tasks.json")));
+        assert!(!filter.matches(Path::new("debug.json")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_rejected() {
+        let result = FileFilter::new(&strings(&["[invalid"]), &[]);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid glob pattern: [invalid"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use tracing::warn;
+
+use crate::sync::{
+    BackupStore, CURRENT_OS, Error, SyncDirection, SyncEvent, SyncEventLog, SyncStateStore,
+    materialize_os_sections,
+};
+
+/// Writes files loaded from the remote storage into the Zed config dir, the same way for the CLI tool
+/// and the language server: with the sections for other OSes emptied and the overwritten files backed up
+#[derive(Debug)]
+pub struct LocalFileWriter {
+    config_dir: PathBuf,
+    backups: BackupStore,
+    state_store: SyncStateStore,
+    event_log: SyncEventLog,
+}
+
+impl LocalFileWriter {
+    #[must_use]
+    pub fn new(config_dir: &Path, data_dir: &Path, backups: BackupStore) -> Self {
+        Self {
+            config_dir: config_dir.to_path_buf(),
+            backups,
+            state_store: SyncStateStore::new(data_dir),
+            event_log: SyncEventLog::new(data_dir),
+        }
+    }
+
+    #[must_use]
+    pub fn file_path(&self, file_name: &str) -> PathBuf {
+        self.config_dir.join(file_name)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn write(
+        &mut self,
+        file_name: &str,
+        content: &str,
+        remote_revision: Option<String>,
+    ) -> Result<()> {
+        let content = match materialize_os_sections(content, CURRENT_OS) {
+            Ok(content) => content,
+            Err(err) => {
+                self.log_failure(file_name, &err, remote_revision);
+                return Err(err.into());
+            }
+        };
+
+        let file_path = self.file_path(file_name);
+        if file_path.exists() {
+            self.backups.back_up(&file_path)?;
+        }
+        fs::write(file_path, &content)?;
+
+        // the file is written already, so failing to keep track of it doesn't fail the load
+        if let Err(err) = self.state_store.record_synced(file_name, &content) {
+            warn!("Failed to record sync state of {file_name}: {err}");
+        }
+        self.log_event(&SyncEvent::new(
+            SyncDirection::Load,
+            file_name,
+            &content,
+            remote_revision,
+            None,
+        ));
+
+        Ok(())
+    }
+
+    /// Records a file that failed to be loaded in the sync event log
+    pub fn log_failure(&self, file_name: &str, error: &Error, remote_revision: Option<String>) {
+        self.log_event(&SyncEvent::new(
+            SyncDirection::Load,
+            file_name,
+            "",
+            remote_revision,
+            Some(error),
+        ));
+    }
+
+    fn log_event(&self, event: &SyncEvent) {
+        if let Err(err) = self.event_log.append(event) {
+            warn!("Failed to log sync event of {}: {err}", event.file);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::{TempDir, prelude::*};
+
+    use super::*;
+    use crate::sync::{SyncEventFilter, SyncResult, content_hash};
+
+    #[test]
+    fn test_written_file_is_materialized_backed_up_recorded_and_logged() -> Result<()> {
+        let config_dir = TempDir::new()?;
+        let data_dir = TempDir::new()?;
+        config_dir.child("settings.json").write_str("{}")?;
+        let mut writer = LocalFileWriter::new(
+            config_dir.path(),
+            data_dir.path(),
+            BackupStore::new(data_dir.path(), 0),
+        );
+
+        writer.write(
+            "settings.json",
+            "{\n  // @os plan9\n  \"theme\": \"Plan 9\"\n  // @end-os\n}\n",
+            Some("abc123".to_string()),
+        )?;
+
+        let materialized = "{\n  // @os plan9\n  // @end-os\n}\n";
+        config_dir.child("settings.json").assert(materialized);
+        assert_eq!(
+            BackupStore::new(data_dir.path(), 0).snapshots()?[0].file_names,
+            ["settings.json"]
+        );
+        assert_eq!(
+            SyncStateStore::new(data_dir.path())
+                .load()?
+                .file("settings.json")
+                .map(|state| state.content_hash.clone()),
+            Some(content_hash(materialized))
+        );
+        let events = SyncEventLog::new(data_dir.path()).query(&SyncEventFilter::default())?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].direction, SyncDirection::Load);
+        assert_eq!(events[0].remote_revision.as_deref(), Some("abc123"));
+        assert_eq!(events[0].result, SyncResult::Ok);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+use crate::sync::{FileFilter, Profile};
+
+/// Sync behavior settings derived from the user config, shared by all sync clients.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    pub file_filter: FileFilter,
+    pub profile: Profile,
+}
@@ -0,0 +1,288 @@
+//! OS-conditional sections in synced JSONC files.
+//!
+//! A section is a run of lines enclosed in comment directives:
+//!
+//! ```jsonc
+//! // @os macos linux
+//! "buffer_font_family": "SF Mono",
+//! // @end-os
+//! ```
+//!
+//! When a file is materialized on a machine, sections for other OSes are emptied, keeping only the directive lines
+//! as placeholders. On sync, the placeholders are filled back in from the remote copy of the file,
+//! so sections for other OSes survive a round trip through a machine that doesn't use them.
+
+use std::collections::HashMap;
+
+use crate::sync::Error;
+
+pub const CURRENT_OS: &str = std::env::consts::OS;
+
+const SECTION_START_DIRECTIVE: &str = "@os";
+const SECTION_END_DIRECTIVE: &str = "@end-os";
+
+enum Segment<'a> {
+    Text(&'a str),
+    Section(Section<'a>),
+}
+
+struct Section<'a> {
+    start_line: &'a str,
+    os_names: Vec<&'a str>,
+    lines: Vec<&'a str>,
+    end_line: &'a str,
+}
+
+impl Section<'_> {
+    fn is_for_os(&self, os: &str) -> bool {
+        self.os_names.contains(&os)
+    }
+
+    // sections are matched between file versions by their OS list and their ordinal among sections with the same list
+    fn key(&self) -> String {
+        self.os_names.join(" ")
+    }
+}
+
+#[must_use]
+pub fn has_os_sections(body: &str) -> bool {
+    body.lines()
+        .any(|line| parse_directive(line, SECTION_START_DIRECTIVE).is_some())
+}
+
+/// Empties sections that don't apply to the given OS, keeping their directive lines as placeholders
+#[allow(clippy::missing_errors_doc)]
+pub fn materialize_os_sections(body: &str, os: &str) -> Result<String, Error> {
+    let mut output = String::with_capacity(body.len());
+
+    for segment in parse_segments(body)? {
+        match segment {
+            Segment::Text(line) => output.push_str(line),
+            Segment::Section(section) => {
+                output.push_str(section.start_line);
+                if section.is_for_os(os) {
+                    output.extend(section.lines);
+                }
+                output.push_str(section.end_line);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Replaces the contents of sections that don't apply to the given OS with their contents from the remote copy
+#[allow(clippy::missing_errors_doc)]
+pub fn restore_foreign_os_sections(local: &str, remote: &str, os: &str) -> Result<String, Error> {
+    let mut remote_sections: HashMap<String, Vec<Vec<&str>>> = HashMap::new();
+    for segment in parse_segments(remote)? {
+        if let Segment::Section(section) = segment {
+            remote_sections
+                .entry(section.key())
+                .or_default()
+                .push(section.lines);
+        }
+    }
+
+    let mut seen_sections: HashMap<String, usize> = HashMap::new();
+    let mut output = String::with_capacity(local.len());
+
+    for segment in parse_segments(local)? {
+        match segment {
+            Segment::Text(line) => output.push_str(line),
+            Segment::Section(section) => {
+                let key = section.key();
+                let ordinal = seen_sections.entry(key.clone()).or_default();
+                let remote_lines = remote_sections
+                    .get(&key)
+                    .and_then(|sections| sections.get(*ordinal));
+                *ordinal += 1;
+
+                output.push_str(section.start_line);
+                match remote_lines {
+                    Some(remote_lines) if !section.is_for_os(os) => {
+                        output.extend(remote_lines.iter().copied());
+                    }
+                    _ => output.extend(section.lines),
+                }
+                output.push_str(section.end_line);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn parse_segments(body: &str) -> Result<Vec<Segment<'_>>, Error> {
+    let mut segments = Vec::new();
+    let mut open_section: Option<Section> = None;
+
+    for (index, line) in body.split_inclusive('\n').enumerate() {
+        if let Some(os_names) = parse_directive(line, SECTION_START_DIRECTIVE) {
+            if open_section.is_some() {
+                return Err(Error::InvalidConfig(format!(
+                    "Nested {SECTION_START_DIRECTIVE} section at line {}",
+                    index + 1
+                )));
+            }
+            if os_names.is_empty() {
+                return Err(Error::InvalidConfig(format!(
+                    "Missing OS names for {SECTION_START_DIRECTIVE} section at line {}",
+                    index + 1
+                )));
+            }
+
+            open_section = Some(Section {
+                start_line: line,
+                os_names,
+                lines: Vec::new(),
+                end_line: "",
+            });
+        } else if parse_directive(line, SECTION_END_DIRECTIVE).is_some() {
+            let Some(mut section) = open_section.take() else {
+                return Err(Error::InvalidConfig(format!(
+                    "Unexpected {SECTION_END_DIRECTIVE} at line {}",
+                    index + 1
+                )));
+            };
+
+            section.end_line = line;
+            segments.push(Segment::Section(section));
+        } else if let Some(section) = open_section.as_mut() {
+            section.lines.push(line);
+        } else {
+            segments.push(Segment::Text(line));
+        }
+    }
+
+    if open_section.is_some() {
+        return Err(Error::InvalidConfig(format!(
+            "Unterminated {SECTION_START_DIRECTIVE} section, missing {SECTION_END_DIRECTIVE}"
+        )));
+    }
+
+    Ok(segments)
+}
+
+// returns directive arguments if the line is a `// <directive> [args...]` comment
+fn parse_directive<'a>(line: &'a str, directive: &str) -> Option<Vec<&'a str>> {
+    let mut words = line.trim().strip_prefix("//")?.split_whitespace();
+
+    if words.next()? == directive {
+        Some(words.collect())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const SYNCED_FILE: &str = r#"{
+  "theme": "One Dark",
+  // @os macos
+  "buffer_font_family": "SF Mono",
+  // @end-os
+  // @os linux windows
+  "buffer_font_family": "Ubuntu Mono",
+  // @end-os
+  "tab_size": 2
+}
+"#;
+
+    #[test]
+    fn test_has_os_sections() {
+        assert!(has_os_sections(SYNCED_FILE));
+        assert!(!has_os_sections(r#"{ "theme": "One Dark" }"#));
+    }
+
+    #[test]
+    fn test_materialize_keeps_sections_for_current_os_only() -> Result<(), Error> {
+        assert_eq!(
+            materialize_os_sections(SYNCED_FILE, "linux")?,
+            r#"{
+  "theme": "One Dark",
+  // @os macos
+  // @end-os
+  // @os linux windows
+  "buffer_font_family": "Ubuntu Mono",
+  // @end-os
+  "tab_size": 2
+}
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_foreign_sections_from_remote_copy() -> Result<(), Error> {
+        let local = r#"{
+  "theme": "Ayu Dark",
+  // @os macos
+  // @end-os
+  // @os linux windows
+  "buffer_font_family": "Fira Code",
+  // @end-os
+  "tab_size": 2
+}
+"#;
+
+        assert_eq!(
+            restore_foreign_os_sections(local, SYNCED_FILE, "linux")?,
+            r#"{
+  "theme": "Ayu Dark",
+  // @os macos
+  "buffer_font_family": "SF Mono",
+  // @end-os
+  // @os linux windows
+  "buffer_font_family": "Fira Code",
+  // @end-os
+  "tab_size": 2
+}
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_keeps_local_sections_missing_remotely() -> Result<(), Error> {
+        let local = r#"{
+  // @os windows
+  "terminal": { "shell": "powershell" },
+  // @end-os
+}
+"#;
+
+        assert_eq!(
+            restore_foreign_os_sections(local, r#"{ "theme": "One Dark" }"#, "macos")?,
+            local
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unterminated_section_is_rejected() {
+        let result = materialize_os_sections("{\n// @os macos\n}\n", "macos");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid config structure: Unterminated @os section, missing @end-os"
+        );
+    }
+
+    #[test]
+    fn test_unexpected_section_end_is_rejected() {
+        let result = materialize_os_sections("{\n// @end-os\n}\n", "macos");
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Invalid config structure: Unexpected @end-os at line 2"
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use jsonc_parser::{
+    ParseOptions,
+    cst::{CstInputValue, CstNode, CstObject, CstObjectProp, CstRootNode},
+    parse_to_serde_value,
+};
+use zed_extension_api::serde_json::{Map, Value};
+
+use crate::sync::Error;
+
+const SETTINGS_FILE_STEM: &str = "settings";
+
+/// A per-machine overlay on top of the shared base `settings.json`.
+///
+/// Machine-local keys (dotted paths like `terminal.shell`) are moved out of the base document on sync
+/// and stored remotely in a separate `settings.<profile>.json` overlay document,
+/// which is merged back into the base document on load.
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    machine_local_keys: Vec<String>,
+}
+
+impl Profile {
+    #[must_use]
+    pub fn new(name: String, machine_local_keys: Vec<String>) -> Self {
+        Self {
+            name,
+            machine_local_keys,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn overlay_file_name(&self) -> String {
+        format!("{SETTINGS_FILE_STEM}.{}.json", self.name)
+    }
+
+    #[must_use]
+    pub fn has_machine_local_keys(&self) -> bool {
+        !self.machine_local_keys.is_empty()
+    }
+
+    #[must_use]
+    pub fn is_overlay_file_name(file_name: &str) -> bool {
+        file_name
+            .strip_prefix(SETTINGS_FILE_STEM)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|rest| rest.strip_suffix(".json"))
+            .is_some_and(|profile_name| !profile_name.is_empty())
+    }
+
+    /// Removes machine-local keys from the base settings document and returns the overlay document built from them.
+    /// The keys are moved with their comments and formatting
+    #[allow(clippy::missing_errors_doc)]
+    pub fn split(&self, root: &CstRootNode) -> Result<String, Error> {
+        let mut overlay = OverlayObject::default();
+
+        for key in &self.machine_local_keys {
+            let segments: Vec<&str> = key.split('.').collect();
+
+            if let Some(prop) = find_property(root, &segments) {
+                overlay.insert(&segments, take_property(prop));
+            }
+        }
+
+        Ok(overlay.render(0))
+    }
+
+    /// Merges the overlay document into the base settings document, overlay values take precedence.
+    /// Returns the merged document and leaves the base one untouched, the overlay properties keep their comments and formatting
+    #[allow(clippy::missing_errors_doc)]
+    pub fn merge(root: &CstRootNode, overlay: &str) -> Result<CstRootNode, Error> {
+        let overlay_root =
+            CstRootNode::parse(overlay, &ParseOptions::default()).map_err(Error::InvalidJson)?;
+        let overlay_values = match parse_to_serde_value(overlay, &ParseOptions::default())
+            .map_err(Error::InvalidJson)?
+        {
+            Some(Value::Object(overlay_values)) => overlay_values,
+            None => Map::new(),
+            Some(_) => {
+                return Err(Error::InvalidConfig(
+                    "Settings profile overlay must be a JSON object".to_string(),
+                ));
+            }
+        };
+
+        // the CST can't take the overlay nodes as they are, so they replace placeholders in the text
+        let merged = CstRootNode::parse(&root.to_string(), &ParseOptions::default())
+            .map_err(Error::InvalidJson)?;
+        let mut replacements = Vec::new();
+        if let Some(overlay_object) = overlay_root.object_value() {
+            merge_object(
+                &merged.object_value_or_set(),
+                &overlay_object,
+                &overlay_values,
+                &mut replacements,
+            );
+        }
+
+        let mut text = merged.to_string();
+        for (index, lines) in replacements.iter().enumerate() {
+            text = replace_placeholder(&text, index, lines);
+        }
+
+        CstRootNode::parse(&text, &ParseOptions::default()).map_err(Error::InvalidJson)
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new(current_hostname(), Vec::new())
+    }
+}
+
+#[must_use]
+pub fn current_hostname() -> String {
+    gethostname::gethostname().to_string_lossy().into_owned()
+}
+
+/// Property key of the placeholders replaced with the overlay properties on merge
+const PLACEHOLDER_KEY_PREFIX: &str = "__settings_sync_overlay_";
+
+/// Overlay properties by key, in the order of the machine-local keys
+#[derive(Debug, Default)]
+struct OverlayObject {
+    props: Vec<(String, OverlayProp)>,
+}
+
+#[derive(Debug)]
+enum OverlayProp {
+    /// Source lines of a moved property, its own-line comments first
+    Moved(Vec<String>),
+    Object(OverlayObject),
+}
+
+impl OverlayObject {
+    fn insert(&mut self, segments: &[&str], lines: Vec<String>) {
+        let Some((first, rest)) = segments.split_first() else {
+            return;
+        };
+        let index = match self.props.iter().position(|(key, _)| key == first) {
+            Some(index) => index,
+            None => {
+                self.props
+                    .push(((*first).to_string(), OverlayProp::Object(Self::default())));
+                self.props.len() - 1
+            }
+        };
+
+        let prop = &mut self.props[index].1;
+        if rest.is_empty() {
+            *prop = OverlayProp::Moved(lines);
+            return;
+        }
+        // a parent key that is machine-local itself already holds the nested one
+        if let OverlayProp::Object(nested) = prop {
+            nested.insert(rest, lines);
+        }
+    }
+
+    fn render(&self, depth: usize) -> String {
+        if self.props.is_empty() {
+            return "{}".to_string();
+        }
+
+        let indent = "  ".repeat(depth + 1);
+        let props: Vec<String> = self
+            .props
+            .iter()
+            .map(|(key, prop)| match prop {
+                OverlayProp::Moved(lines) => lines
+                    .iter()
+                    .map(|line| format!("{indent}{line}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                OverlayProp::Object(nested) => format!(
+                    "{indent}{}: {}",
+                    Value::String(key.clone()),
+                    nested.render(depth + 1)
+                ),
+            })
+            .collect();
+
+        format!("{{\n{}\n{}}}", props.join(",\n"), "  ".repeat(depth))
+    }
+}
+
+fn find_property(root: &CstRootNode, segments: &[&str]) -> Option<CstObjectProp> {
+    let (last, parents) = segments.split_last()?;
+
+    let mut object = root.object_value();
+    for segment in parents {
+        object = object
+            .and_then(|object| object.get(segment))
+            .and_then(|prop| prop.object_value());
+    }
+
+    object.and_then(|object| object.get(last))
+}
+
+/// Removes the property along with its own-line comments and returns their source lines
+fn take_property(prop: CstObjectProp) -> Vec<String> {
+    let comments = leading_comments(&prop);
+    let mut lines: Vec<String> = comments.iter().map(ToString::to_string).collect();
+    lines.push(prop.to_string());
+
+    for comment in comments {
+        comment.remove();
+    }
+    prop.remove();
+
+    lines
+}
+
+/// Comments on the lines right above the property, a comment after the previous property on its line belongs to that one
+fn leading_comments(prop: &CstObjectProp) -> Vec<CstNode> {
+    let mut trivia = Vec::new();
+    let mut sibling = prop.previous_sibling();
+    while let Some(node) = sibling {
+        if !node.is_comment() && !node.is_newline() && !node.is_whitespace() {
+            break;
+        }
+        sibling = node.previous_sibling();
+        trivia.push(node);
+    }
+    trivia.reverse();
+
+    trivia
+        .into_iter()
+        .skip_while(|node| !node.is_newline())
+        .filter(CstNode::is_comment)
+        .collect()
+}
+
+fn merge_object(
+    target: &CstObject,
+    source: &CstObject,
+    values: &Map<String, Value>,
+    replacements: &mut Vec<Vec<String>>,
+) {
+    for (key, value) in values {
+        let Some(source_prop) = source.get(key) else {
+            continue;
+        };
+        let target_prop = target.get(key);
+
+        if let (Some(nested_target), Some(nested_source), Value::Object(nested_values)) = (
+            target_prop.as_ref().and_then(CstObjectProp::object_value),
+            source_prop.object_value(),
+            value,
+        ) {
+            merge_object(&nested_target, &nested_source, nested_values, replacements);
+            continue;
+        }
+
+        let mut lines: Vec<String> = leading_comments(&source_prop)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        lines.push(source_prop.to_string());
+
+        let placeholder_key = format!("{PLACEHOLDER_KEY_PREFIX}{}", replacements.len());
+        match target_prop {
+            Some(target_prop) => {
+                let index = target_prop.property_index();
+                target_prop.remove();
+                target.insert(index, &placeholder_key, CstInputValue::Null);
+            }
+            None => {
+                target.append(&placeholder_key, CstInputValue::Null);
+            }
+        }
+        replacements.push(lines);
+    }
+}
+
+// the placeholder property `"<key>": null` is replaced with the lines, indented like it
+fn replace_placeholder(text: &str, index: usize, lines: &[String]) -> String {
+    let quoted_key = format!("\"{PLACEHOLDER_KEY_PREFIX}{index}\"");
+    let Some(start) = text.find(&quoted_key) else {
+        return text.to_string();
+    };
+    let Some(end) = text[start..]
+        .find("null")
+        .map(|offset| start + offset + "null".len())
+    else {
+        return text.to_string();
+    };
+
+    let line_start = text[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let indent: String = text[line_start..start]
+        .chars()
+        .take_while(|char| char.is_whitespace())
+        .collect();
+    let replacement = lines.join(&format!("\n{indent}"));
+
+    format!("{}{replacement}{}", &text[..start], &text[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use zed_extension_api::serde_json::json;
+
+    use super::*;
+
+    fn parse(text: &str) -> CstRootNode {
+        CstRootNode::parse(text, &ParseOptions::default()).unwrap()
+    }
+
+    fn to_value(text: &str) -> Value {
+        parse_to_serde_value(text, &ParseOptions::default())
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_overlay_file_name() {
+        let profile = Profile::new("desktop".to_string(), Vec::new());
+
+        assert_eq!(profile.overlay_file_name(), "settings.desktop.json");
+        assert!(Profile::is_overlay_file_name("settings.desktop.json"));
+        assert!(!Profile::is_overlay_file_name("settings.json"));
+        assert!(!Profile::is_overlay_file_name("keymap.json"));
+    }
+
+    #[test]
+    fn test_split_moves_machine_local_keys_to_overlay() -> Result<(), Error> {
+        let profile = Profile::new(
+            "desktop".to_string(),
+            vec!["buffer_font_size".to_string(), "terminal.shell".to_string()],
+        );
+        let root = parse(
+            r#"{
+                // shared
+                "theme": "One Dark",
+                "buffer_font_size": 16,
+                "terminal": {
+                    "shell": { "program": "fish" },
+                    "font_size": 14
+                }
+            }"#,
+        );
+
+        let overlay = profile.split(&root)?;
+
+        assert_eq!(
+            to_value(&overlay),
+            json!({
+                "buffer_font_size": 16,
+                "terminal": { "shell": { "program": "fish" } }
+            })
+        );
+        assert_eq!(
+            to_value(&root.to_string()),
+            json!({
+                "theme": "One Dark",
+                "terminal": { "font_size": 14 }
+            })
+        );
+        assert!(root.to_string().contains("// shared"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_without_machine_local_keys_present() -> Result<(), Error> {
+        let profile = Profile::new("laptop".to_string(), vec!["terminal.shell".to_string()]);
+        let root = parse(r#"{ "theme": "One Dark" }"#);
+
+        assert_eq!(to_value(&profile.split(&root)?), json!({}));
+        assert_eq!(to_value(&root.to_string()), json!({ "theme": "One Dark" }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_applies_overlay_on_top_of_base() -> Result<(), Error> {
+        let root = parse(
+            r#"{
+                // shared
+                "theme": "One Dark",
+                "buffer_font_size": 12,
+                "terminal": { "font_size": 14 }
+            }"#,
+        );
+
+        let merged = Profile::merge(
+            &root,
+            r#"{ "buffer_font_size": 16, "terminal": { "shell": "system" } }"#,
+        )?;
+
+        assert_eq!(
+            to_value(&merged.to_string()),
+            json!({
+                "theme": "One Dark",
+                "buffer_font_size": 16,
+                "terminal": { "font_size": 14, "shell": "system" }
+            })
+        );
+        assert!(merged.to_string().contains("// shared"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_comment_on_machine_local_key_survives_split_and_merge() -> Result<(), Error> {
+        let profile = Profile::new(
+            "desktop".to_string(),
+            vec!["buffer_font_size".to_string(), "terminal.shell".to_string()],
+        );
+        let root = parse(
+            r#"{
+  "theme": "One Dark",
+  // bigger font on the external monitor
+  "buffer_font_size": 16,
+  "terminal": {
+    "font_size": 14,
+    /* fish is only installed here */
+    "shell": { "program": "fish" }
+  }
+}"#,
+        );
+
+        let overlay = profile.split(&root)?;
+
+        assert!(overlay.contains("// bigger font on the external monitor"));
+        assert!(overlay.contains("/* fish is only installed here */"));
+        assert!(
+            !root
+                .to_string()
+                .contains("// bigger font on the external monitor")
+        );
+        assert!(
+            !root
+                .to_string()
+                .contains("/* fish is only installed here */")
+        );
+
+        let merged = Profile::merge(&root, &overlay)?.to_string();
+
+        assert!(merged.contains("// bigger font on the external monitor"));
+        assert!(merged.contains("/* fish is only installed here */"));
+        assert_eq!(
+            to_value(&merged),
+            json!({
+                "theme": "One Dark",
+                "buffer_font_size": 16,
+                "terminal": { "font_size": 14, "shell": { "program": "fish" } }
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_rejects_non_object_overlay() {
+        let root = parse("{}");
+
+        assert!(matches!(
+            Profile::merge(&root, "[]"),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use jsonc_parser::{ParseOptions, parse_to_serde_value};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zed_extension_api::serde_json::{Value, from_str, to_string_pretty};
+
+const STATE_DIR_NAME: &str = "settings-sync";
+const STATE_FILE_NAME: &str = "state.json";
+const SHORT_ID_LEN: usize = 7;
+
+// serializes read-modify-write cycles of concurrent syncs within a process
+static STATE_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Locally persisted metadata of the last successful sync of each config file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    files: BTreeMap<String, FileSyncState>,
+}
+
+impl SyncState {
+    #[must_use]
+    pub fn file(&self, file_name: &str) -> Option<&FileSyncState> {
+        self.files.get(file_name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSyncState {
+    /// Hash of the file content both sides had right after the sync, see [`content_hash`]
+    pub content_hash: String,
+    pub synced_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncStateStore {
+    path: PathBuf,
+}
+
+impl SyncStateStore {
+    #[must_use]
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(STATE_DIR_NAME).join(STATE_FILE_NAME),
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load(&self) -> Result<SyncState> {
+        if !self.path.try_exists()? {
+            return Ok(SyncState::default());
+        }
+
+        from_str(&fs::read_to_string(&self.path)?)
+            .with_context(|| format!("Failed to parse sync state file {}", self.path.display()))
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn record_synced(&self, file_name: &str, content: &str) -> Result<()> {
+        let _guard = STATE_FILE_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let mut state = self.load()?;
+        state.files.insert(
+            file_name.to_string(),
+            FileSyncState {
+                content_hash: content_hash(content),
+                synced_at: Utc::now(),
+            },
+        );
+
+        if let Some(state_dir) = self.path.parent() {
+            fs::create_dir_all(state_dir)?;
+        }
+        fs::write(&self.path, to_string_pretty(&state)?)?;
+
+        Ok(())
+    }
+}
+
+/// How a config file relates to its remote copy and the last sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    InSync,
+    LocallyModified,
+    RemotelyModified,
+    Diverged,
+    MissingLocally,
+    MissingRemotely,
+    Error,
+}
+
+impl Display for SyncStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SyncStatus::InSync => "in sync",
+            SyncStatus::LocallyModified => "locally modified",
+            SyncStatus::RemotelyModified => "remotely modified",
+            SyncStatus::Diverged => "diverged",
+            SyncStatus::MissingLocally => "missing locally",
+            SyncStatus::MissingRemotely => "missing remotely",
+            SyncStatus::Error => "error",
+        };
+
+        // respecting width and alignment of the table columns
+        f.pad(text)
+    }
+}
+
+/// Compares both sides of the file with each other and with the content hash of the last sync
+#[must_use]
+pub fn sync_status(
+    local: Option<&String>,
+    remote: Option<&String>,
+    file_state: Option<&FileSyncState>,
+) -> SyncStatus {
+    let (local, remote) = match (local, remote) {
+        (Some(local), Some(remote)) => (local, remote),
+        (Some(_), None) => return SyncStatus::MissingRemotely,
+        (None, _) => return SyncStatus::MissingLocally,
+    };
+
+    let local_hash = content_hash(local);
+    let remote_hash = content_hash(remote);
+    if local_hash == remote_hash {
+        return SyncStatus::InSync;
+    }
+
+    // the side whose content still matches the last sync is the one that hasn't changed since
+    match file_state.map(|state| &state.content_hash) {
+        Some(synced_hash) if *synced_hash == remote_hash => SyncStatus::LocallyModified,
+        Some(synced_hash) if *synced_hash == local_hash => SyncStatus::RemotelyModified,
+        _ => SyncStatus::Diverged,
+    }
+}
+
+/// Hashes the parsed JSON value of the content, so that formatting and comments don't count as changes
+#[must_use]
+pub fn content_hash(content: &str) -> String {
+    let normalized = match parse_to_serde_value(content, &ParseOptions::default()) {
+        Ok(Some(value)) => sort_keys(value).to_string(),
+        _ => content.to_string(),
+    };
+
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Abbreviated content hash or Gist revision, short enough to be recognizable like abbreviated git commit hashes
+#[must_use]
+pub fn short_id(id: &str) -> &str {
+    id.get(..SHORT_ID_LEN).unwrap_or(id)
+}
+
+// key order isn't significant, and merging a profile overlay may change it
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_fs::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_content_hash_ignores_formatting() {
+        assert_eq!(
+            content_hash(r#"{ "theme": "One Dark", "tab_size": 2 }"#),
+            content_hash(
+                r#"{
+                    // indentation
+                    "tab_size": 2,
+                    "theme": "One Dark",
+                }"#
+            )
+        );
+        assert_ne!(
+            content_hash(r#"{ "tab_size": 2 }"#),
+            content_hash(r#"{ "tab_size": 4 }"#)
+        );
+    }
+
+    #[test]
+    fn test_recorded_sync_is_loaded() -> Result<()> {
+        let data_dir = TempDir::new()?;
+        let store = SyncStateStore::new(data_dir.path());
+
+        assert!(store.load()?.file("tasks.json").is_none());
+
+        store.record_synced("tasks.json", "[]")?;
+
+        let state = store.load()?;
+        let file_state = state.file("tasks.json").context("Missing file state")?;
+        assert_eq!(file_state.content_hash, content_hash("[]"));
+        assert!(state.file("keymap.json").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_id_is_cut_to_seven_chars() {
+        assert_eq!(short_id("0123456789abcdef"), "0123456");
+        assert_eq!(short_id("abc"), "abc");
+    }
+}
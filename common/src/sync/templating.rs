@@ -0,0 +1,239 @@
+use jsonc_parser::cst::{CstNode, CstRootNode};
+use paths as zed_paths;
+use zed_extension_api::serde_json::Value;
+
+use crate::sync::current_hostname;
+
+// a hostname elsewhere, e.g. in `ssh://mac:22`, names a specific machine and must stay as it is
+const PATH_ONLY_VARIABLES: [&str; 1] = ["HOSTNAME"];
+
+/// Machine-specific values that are replaced with `${NAME}` placeholders in synced files
+/// and expanded back on load, so paths like `/home/alice/.cargo/bin` work across machines.
+#[derive(Debug, Clone)]
+pub struct TemplateVariables {
+    // sorted by value length, longest first, so nested values (config dir inside home dir) are replaced first
+    variables: Vec<(&'static str, String)>,
+}
+
+impl TemplateVariables {
+    #[must_use]
+    pub fn new(variables: Vec<(&'static str, String)>) -> Self {
+        let mut variables: Vec<_> = variables
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect();
+        variables.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+
+        Self { variables }
+    }
+
+    #[must_use]
+    pub fn local() -> Self {
+        Self::new(vec![
+            ("HOME", zed_paths::home_dir().to_string_lossy().into_owned()),
+            (
+                "ZED_CONFIG_DIR",
+                zed_paths::config_dir().to_string_lossy().into_owned(),
+            ),
+            ("HOSTNAME", current_hostname()),
+        ])
+    }
+
+    #[must_use]
+    pub fn templatize(&self, text: &str) -> String {
+        let path_like = is_path_like(text);
+
+        self.variables
+            .iter()
+            .filter(|(name, _)| path_like || !PATH_ONLY_VARIABLES.contains(name))
+            .fold(text.to_string(), |text, (name, value)| {
+                replace_bounded(&text, value, &placeholder(name))
+            })
+    }
+
+    #[must_use]
+    pub fn expand(&self, text: &str) -> String {
+        self.variables
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| {
+                text.replace(&placeholder(name), value)
+            })
+    }
+
+    pub fn templatize_values(&self, root: &CstRootNode) {
+        if let Some(value) = root.value() {
+            transform_string_values(value, &|text| self.templatize(text));
+        }
+    }
+
+    pub fn expand_values(&self, root: &CstRootNode) {
+        if let Some(value) = root.value() {
+            transform_string_values(value, &|text| self.expand(text));
+        }
+    }
+}
+
+fn placeholder(name: &str) -> String {
+    format!("${{{name}}}")
+}
+
+// absolute Unix or Windows paths, home-relative paths and UNC paths
+fn is_path_like(text: &str) -> bool {
+    let mut chars = text.chars();
+    let is_drive_path = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.next() == Some(':')
+        && matches!(chars.next(), Some('/' | '\\'));
+
+    text.starts_with('/') || text.starts_with("~/") || text.starts_with("\\\\") || is_drive_path
+}
+
+// replaces only occurrences that aren't a part of a longer word or path segment,
+// e.g. hostname "mac" inside "macos" or home dir "/home/al" inside "/home/alice"
+fn replace_bounded(text: &str, from: &str, to: &str) -> String {
+    let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | '.');
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find(from) {
+        let (before, after) = (&rest[..index], &rest[index + from.len()..]);
+        let bounded_before = before.chars().next_back().is_none_or(|c| !is_word_char(c));
+        let bounded_after = after.chars().next().is_none_or(|c| !is_word_char(c));
+
+        result.push_str(before);
+        result.push_str(if bounded_before && bounded_after {
+            to
+        } else {
+            from
+        });
+        rest = after;
+    }
+    result.push_str(rest);
+
+    result
+}
+
+fn transform_string_values(node: CstNode, transform: &dyn Fn(&str) -> String) {
+    if let Some(object) = node.as_object() {
+        for prop in object.properties() {
+            if let Some(value) = prop.value() {
+                transform_string_values(value, transform);
+            }
+        }
+    } else if let Some(array) = node.as_array() {
+        for element in array.elements() {
+            transform_string_values(element, transform);
+        }
+    } else if let Some(string_lit) = node.as_string_lit()
+        && let Ok(text) = string_lit.decoded_value()
+    {
+        let transformed = transform(&text);
+        if transformed != text {
+            string_lit.set_raw_value(Value::String(transformed).to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use jsonc_parser::ParseOptions;
+
+    use super::*;
+
+    fn variables() -> TemplateVariables {
+        TemplateVariables::new(vec![
+            ("HOME", "/home/alice".to_string()),
+            ("ZED_CONFIG_DIR", "/home/alice/.config/zed".to_string()),
+            ("HOSTNAME", "mac".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_templatize_replaces_local_values() {
+        let variables = variables();
+
+        assert_eq!(
+            variables.templatize("/home/alice/.cargo/bin/rust-analyzer"),
+            "${HOME}/.cargo/bin/rust-analyzer"
+        );
+        assert_eq!(
+            variables.templatize("/home/alice/.config/zed/themes"),
+            "${ZED_CONFIG_DIR}/themes"
+        );
+        assert_eq!(
+            variables.templatize("/Volumes/mac/projects"),
+            "/Volumes/${HOSTNAME}/projects"
+        );
+    }
+
+    #[test]
+    fn test_templatize_keeps_hostname_outside_paths() {
+        let variables = variables();
+
+        assert_eq!(variables.templatize("ssh://mac:22"), "ssh://mac:22");
+        assert_eq!(variables.templatize("mac"), "mac");
+        assert_eq!(
+            variables.templatize("\\\\mac\\share"),
+            "\\\\${HOSTNAME}\\share"
+        );
+    }
+
+    #[test]
+    fn test_templatize_skips_partial_matches() {
+        let variables = variables();
+
+        assert_eq!(variables.templatize("/home/alicia/bin"), "/home/alicia/bin");
+        assert_eq!(variables.templatize("macos"), "macos");
+        assert_eq!(variables.templatize("mac-mini"), "mac-mini");
+    }
+
+    #[test]
+    fn test_expand_replaces_placeholders() {
+        let variables = TemplateVariables::new(vec![
+            ("HOME", "/Users/alice".to_string()),
+            ("ZED_CONFIG_DIR", "/Users/alice/.config/zed".to_string()),
+            ("HOSTNAME", String::new()),
+        ]);
+
+        assert_eq!(
+            variables.expand("${HOME}/.cargo/bin/rust-analyzer"),
+            "/Users/alice/.cargo/bin/rust-analyzer"
+        );
+        assert_eq!(
+            variables.expand("${ZED_CONFIG_DIR}/themes"),
+            "/Users/alice/.config/zed/themes"
+        );
+        assert_eq!(variables.expand("${HOSTNAME}"), "${HOSTNAME}");
+    }
+
+    #[test]
+    fn test_templatize_values_keeps_comments() {
+        let root = CstRootNode::parse(
+            r#"{
+  // rust-analyzer from rustup
+  "lsp": {
+    "rust-analyzer": {
+      "binary": { "path": "/home/alice/.cargo/bin/rust-analyzer", "arguments": ["/home/alice"] }
+    }
+  }
+}"#,
+            &ParseOptions::default(),
+        )
+        .unwrap();
+
+        variables().templatize_values(&root);
+
+        assert_eq!(
+            root.to_string(),
+            r#"{
+  // rust-analyzer from rustup
+  "lsp": {
+    "rust-analyzer": {
+      "binary": { "path": "${HOME}/.cargo/bin/rust-analyzer", "arguments": ["${HOME}"] }
+    }
+  }
+}"#
+        );
+    }
+}
@@ -1,27 +1,137 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Result, bail};
+#[double]
 use common::sync::GithubClient;
+use common::{
+    config::{Config, SyncNotifications, SyncTrigger},
+    sync::{Client as SyncClient, GistAccess, SyncOptions},
+};
+use debug_ignore::DebugIgnore;
+use mockall_double::double;
 #[cfg(not(test))]
 use tower_lsp::Client as LspClient;
+use tracing::{error, info};
 
 #[cfg(test)]
 use crate::mocks::MockLspClient as LspClient;
+use crate::remote_snapshot::RemoteSnapshot;
+use crate::sync_reporter::SyncReporter;
 #[cfg(test)]
 use crate::watching::MockPathStore as PathStore;
 #[cfg(not(test))]
 use crate::watching::PathStore;
+use crate::watching::{OpenDocuments, ZedConfigFilePath};
 
 #[derive(Debug)]
 pub struct AppState {
     pub watched_paths: PathStore,
+    pub sync_options: SyncOptions,
+    pub sync_client: DebugIgnore<Arc<dyn SyncClient>>,
+    pub reporter: Arc<SyncReporter>,
+    pub sync_trigger: SyncTrigger,
 }
 
 impl AppState {
-    pub fn new(gist_id: String, github_token: String, lsp_client: Arc<LspClient>) -> Result<Self> {
-        let sync_client = Arc::new(GithubClient::new(gist_id, github_token)?);
-        let watched_paths = PathStore::new(sync_client, lsp_client)?;
+    pub fn new(
+        setup: SyncSetup,
+        lsp_client: Arc<LspClient>,
+        open_documents: OpenDocuments,
+        remote_snapshot: Arc<RemoteSnapshot>,
+    ) -> Result<Self> {
+        let SyncSetup {
+            sync_client,
+            sync_options,
+            notifications,
+            sync_trigger,
+        } = setup;
+        let reporter = Arc::new(SyncReporter::new(lsp_client, notifications));
+        let watched_paths = PathStore::new(
+            Arc::clone(&sync_client),
+            Arc::clone(&reporter),
+            sync_trigger,
+            open_documents,
+            remote_snapshot,
+        )?;
 
-        Ok(Self { watched_paths })
+        Ok(Self {
+            watched_paths,
+            sync_options,
+            sync_client: DebugIgnore(sync_client),
+            reporter,
+            sync_trigger,
+        })
+    }
+
+    /// Switches to the sync client of the given setup, the watched paths excluded by its filter are unwatched
+    pub fn reload(&mut self, setup: SyncSetup) {
+        let SyncSetup {
+            sync_client,
+            sync_options,
+            notifications,
+            sync_trigger,
+        } = setup;
+        self.watched_paths.set_sync_client(Arc::clone(&sync_client));
+        self.watched_paths.set_sync_trigger(sync_trigger);
+        self.sync_client = DebugIgnore(sync_client);
+        self.sync_options = sync_options;
+        self.reporter.set_notifications(notifications);
+        self.sync_trigger = sync_trigger;
+
+        for path in self.watched_paths.watched_paths() {
+            if ZedConfigFilePath::from_path(path.clone(), &self.sync_options.file_filter).is_err() {
+                match self.watched_paths.unwatch(&path) {
+                    Ok(()) => info!("Unwatching path excluded from sync: {}", path.display()),
+                    Err(err) => error!("Failed to stop watching path: {err}"),
+                }
+            }
+        }
+    }
+}
+
+/// Sync client and options built from a config, not used for syncing until the app state is reloaded with them
+pub struct SyncSetup {
+    sync_client: Arc<dyn SyncClient>,
+    sync_options: SyncOptions,
+    notifications: SyncNotifications,
+    sync_trigger: SyncTrigger,
+}
+
+impl SyncSetup {
+    pub fn new(config: &Config) -> Result<Self> {
+        Self::with_github_client(config).map(|(setup, _)| setup)
+    }
+
+    /// Builds the setup failing if the configured gist can't be updated with the configured token
+    pub async fn validated(config: &Config) -> Result<Self> {
+        let (setup, github_client) = Self::with_github_client(config)?;
+
+        match github_client.gist_access().await? {
+            GistAccess::Writable => Ok(setup),
+            GistAccess::ReadOnly { owner } => bail!(
+                "Gist {} belongs to {owner}, the token can't update it",
+                config.gist_id()
+            ),
+            GistAccess::NotFound => bail!("Gist {} not found", config.gist_id()),
+        }
+    }
+
+    fn with_github_client(config: &Config) -> Result<(Self, Arc<GithubClient>)> {
+        let sync_options = config.sync_options()?;
+        let github_client = Arc::new(GithubClient::new(
+            config.gist_id().into(),
+            config.github_token().into(),
+            sync_options.clone(),
+        )?);
+
+        Ok((
+            Self {
+                sync_client: Arc::clone(&github_client) as Arc<dyn SyncClient>,
+                sync_options,
+                notifications: config.notifications(),
+                sync_trigger: config.sync_trigger(),
+            },
+            github_client,
+        ))
     }
 }
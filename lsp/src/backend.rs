@@ -1,29 +1,58 @@
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex, OnceLock};
-
-use anyhow::Result;
-use common::config::Config;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use anyhow::{Context, Result, anyhow};
+use common::{
+    config::{Config, GITHUB_TOKEN_ENV_VAR, SyncTrigger},
+    sync::{Client as SyncClient, FileFilter, LocalFileData, SyncStateStore, sync_status},
+};
+#[cfg(not(test))]
+use paths as zed_paths;
+#[cfg(test)]
+use test_support::zed_paths;
 #[cfg(not(test))]
 use tower_lsp::Client as LspClient;
 use tower_lsp::jsonrpc::Result as LspResult;
-use tower_lsp::lsp_types::{DidCloseTextDocumentParams, DidOpenTextDocumentParams};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionOrCommand, CodeActionParams, CodeActionProviderCapability,
+    CodeActionResponse, CodeLens, CodeLensOptions, CodeLensParams, ConfigurationItem, Diagnostic,
+    DidChangeConfigurationParams, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, MarkupContent, MarkupKind, Position, SaveOptions,
+    TextDocumentSyncKind, TextDocumentSyncSaveOptions, Url,
+};
 use tower_lsp::{
     LanguageServer,
     lsp_types::{
-        InitializeParams, InitializeResult, InitializedParams, ServerCapabilities, ServerInfo,
-        TextDocumentSyncCapability, TextDocumentSyncOptions, WorkspaceServerCapabilities,
+        ExecuteCommandOptions, ExecuteCommandParams, InitializeParams, InitializeResult,
+        InitializedParams, MessageType, ServerCapabilities, ServerInfo, TextDocumentSyncCapability,
+        TextDocumentSyncOptions, WorkspaceServerCapabilities,
     },
 };
 use tracing::{debug, error, info, instrument};
-use zed_extension_api::serde_json::from_value;
+use zed_extension_api::serde_json::{Value, from_value};
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, SyncSetup};
+use crate::code_actions::{
+    diagnostics_with_code, exclude_from_sync, insert_config_skeleton, quick_fix,
+    replace_masked_token,
+};
+use crate::code_lens::sync_status_lens;
+use crate::commands::{self, COMMANDS, Command};
+use crate::diagnostics::{MASKED_TOKEN_CODE, NOT_CONFIGURED_CODE, config_diagnostics, offset_at};
+use crate::hover::{hover_markdown, key_path_at};
 #[cfg(test)]
 use crate::mocks::MockLspClient as LspClient;
-use crate::watching::{ZedConfigFilePath, ZedConfigPathError};
+use crate::remote_snapshot::RemoteSnapshot;
+use crate::sync_reporter::SyncReporter;
+use crate::watching::{OpenDocuments, ZedConfigFilePath, ZedConfigPathError};
 
 const CARGO_PKG_NAME: &str = env!("CARGO_PKG_NAME");
 const CARGO_PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
+const NOT_CONFIGURED_MESSAGE: &str =
+    "Settings sync is not configured, see the problems reported in your settings.json";
 
 #[derive(Debug)]
 pub struct Backend {
@@ -32,6 +61,10 @@ pub struct Backend {
     // because LanguageServer trait methods accept &self (not &mut self).
     app_state: OnceLock<Mutex<AppState>>,
     lsp_client: LspClient,
+    // shared with the file watcher, which skips the saved documents in save mode
+    documents: OpenDocuments,
+    // shared with the file watcher, which invalidates it after syncing
+    remote_snapshot: Arc<RemoteSnapshot>,
 }
 
 impl Backend {
@@ -39,42 +72,365 @@ impl Backend {
         Self {
             app_state: OnceLock::new(),
             lsp_client,
+            documents: OpenDocuments::default(),
+            remote_snapshot: Arc::default(),
         }
     }
 
-    fn watch_path(&self, path: PathBuf) -> Result<()> {
-        let info_msg = format!("Watching path: {}", path.display());
+    fn build_app_state(&self, initialization_options: Option<Value>) -> Result<AppState> {
+        let options = initialization_options.ok_or(anyhow!(
+            "initialization_options are missing from LSP server configuration"
+        ))?;
+        let config: Config =
+            from_value(options).context("Failed to deserialize initialization_options")?;
+
+        AppState::new(
+            SyncSetup::new(&config)?,
+            Arc::new(self.lsp_client.clone()),
+            Arc::clone(&self.documents),
+            Arc::clone(&self.remote_snapshot),
+        )
+        .context("Failed to build the app state")
+    }
+
+    fn start(&self, mut app_state: AppState) -> Result<()> {
+        app_state.watched_paths.start_watcher();
 
-        #[allow(clippy::expect_used)]
         self.app_state
+            .set(Mutex::new(app_state))
+            .map_err(|_| anyhow!("App state was already initialized"))
+    }
+
+    fn app_state(&self) -> Result<MutexGuard<'_, AppState>> {
+        let app_state = self
+            .app_state
             .get()
-            .expect("App state must be initialized")
+            .ok_or(anyhow!(NOT_CONFIGURED_MESSAGE))?;
+
+        #[allow(clippy::expect_used)]
+        Ok(app_state
             .lock()
-            .expect("Watched paths store mutex is poisoned")
-            .watched_paths
-            .watch(path)?;
+            .expect("Watched paths store mutex is poisoned"))
+    }
+
+    fn watch_path(&self, path: PathBuf) -> Result<()> {
+        let info_msg = format!("Watching path: {}", path.display());
+
+        self.app_state()?.watched_paths.watch(path)?;
 
         info!("{}", info_msg);
 
         Ok(())
     }
 
-    fn unwatch_path(&self, path: &Path) -> Result<()> {
-        let info_msg = format!("Unwatching path: {}", path.display());
+    // the remote files or the local ones changed, so the cached remote files and the code lenses are outdated
+    async fn refresh_sync_status(&self) {
+        self.remote_snapshot.invalidate();
 
+        if let Err(err) = self.lsp_client.code_lens_refresh().await {
+            debug!("Code lens refresh is not available: {}", err);
+        }
+    }
+
+    // the watcher would push the pulled files right back, creating a gist revision with no changes
+    async fn pull_unwatched(&self, sync_client: &dyn SyncClient) -> Result<String> {
+        let watched_paths = self.app_state()?.watched_paths.watched_paths();
+        let mut unwatched_paths = Vec::new();
+        for path in watched_paths {
+            match self.unwatch_path(&path) {
+                Ok(()) => unwatched_paths.push(path),
+                Err(err) => error!("Failed to stop watching path: {}", err),
+            }
+        }
+
+        let result = commands::pull(sync_client).await;
+
+        for path in unwatched_paths {
+            if let Err(err) = self.watch_path(path) {
+                error!("Failed to start watching path: {}", err);
+            }
+        }
+
+        result
+    }
+
+    // the documents opened before the config was loaded or changed aren't watched yet
+    fn watch_open_documents(&self) {
+        let Some(file_filter) = self.file_filter() else {
+            return;
+        };
         #[allow(clippy::expect_used)]
-        self.app_state
-            .get()
-            .expect("App state must be initialized")
+        let uris: Vec<Url> = self
+            .documents
             .lock()
-            .expect("Watched paths store mutex is poisoned")
-            .watched_paths
-            .unwatch(path)?;
+            .expect("Documents mutex is poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        let Ok(watched_paths) = self
+            .app_state()
+            .map(|app_state| app_state.watched_paths.watched_paths())
+        else {
+            return;
+        };
+
+        for uri in uris {
+            let Ok(path) = ZedConfigFilePath::from_file_uri(&uri, &file_filter) else {
+                continue;
+            };
+            let path_to_watch = path.to_watched_path_buf();
+            if !watched_paths.contains(&path_to_watch)
+                && let Err(err) = self.watch_path(path_to_watch)
+            {
+                error!("Failed to start watching path: {}", err);
+            }
+        }
+    }
+
+    // not configured sync has no files to sync
+    fn file_filter(&self) -> Option<FileFilter> {
+        self.app_state()
+            .ok()
+            .map(|app_state| app_state.sync_options.file_filter.clone())
+    }
+
+    fn sync_client(&self) -> Result<Arc<dyn SyncClient>> {
+        Ok(Arc::clone(&self.app_state()?.sync_client.0))
+    }
+
+    // the current sync client is kept if the new config can't be used
+    async fn reload_config(&self, pushed_settings: Value) -> Result<String> {
+        let config = self.workspace_config(pushed_settings).await?;
+        let setup = SyncSetup::validated(&config).await?;
+
+        match self.app_state.get() {
+            Some(_) => self.app_state()?.reload(setup),
+            None => self.start(AppState::new(
+                setup,
+                Arc::new(self.lsp_client.clone()),
+                Arc::clone(&self.documents),
+                Arc::clone(&self.remote_snapshot),
+            )?)?,
+        }
+        self.watch_open_documents();
+        self.refresh_sync_status().await;
+
+        Ok("Reloaded the settings sync config".to_string())
+    }
+
+    // unlike initialization_options, the server `settings` are changed by Zed without restarting the server
+    async fn workspace_config(&self, pushed_settings: Value) -> Result<Config> {
+        let pulled_settings = match self
+            .lsp_client
+            .configuration(vec![ConfigurationItem::default()])
+            .await
+        {
+            Ok(values) => values.into_iter().next(),
+            Err(err) => {
+                debug!("Failed to pull the workspace configuration: {}", err);
+                None
+            }
+        };
+
+        match pulled_settings
+            .filter(has_settings)
+            .or(Some(pushed_settings).filter(has_settings))
+        {
+            Some(settings) => {
+                from_value(settings).context("Failed to deserialize the settings-sync settings")
+            }
+            None => Config::from_settings_file(),
+        }
+    }
+
+    // `None` if the saved files are synced by the file watcher
+    fn save_sync_handles(&self) -> Result<Option<(Arc<dyn SyncClient>, Arc<SyncReporter>)>> {
+        let app_state = self.app_state()?;
+        if app_state.sync_trigger != SyncTrigger::Save {
+            return Ok(None);
+        }
+
+        Ok(Some((
+            Arc::clone(&app_state.sync_client.0),
+            Arc::clone(&app_state.reporter),
+        )))
+    }
+
+    fn unwatch_path(&self, path: &Path) -> Result<()> {
+        let info_msg = format!("Unwatching path: {}", path.display());
+
+        self.app_state()?.watched_paths.unwatch(path)?;
 
         info!("{}", info_msg);
 
         Ok(())
     }
+
+    fn set_document_text(&self, uri: &Url, text: Option<&str>) {
+        #[allow(clippy::expect_used)]
+        let mut documents = self.documents.lock().expect("Documents mutex is poisoned");
+        match text {
+            Some(text) => documents.insert(uri.clone(), text.to_string()),
+            None => documents.remove(uri),
+        };
+    }
+
+    fn document_text(&self, uri: &Url) -> Option<String> {
+        #[allow(clippy::expect_used)]
+        self.documents
+            .lock()
+            .expect("Documents mutex is poisoned")
+            .get(uri)
+            .cloned()
+    }
+
+    // the content of the open settings file may be ahead of the one on disk
+    fn settings_text(&self, settings_uri: &Url) -> Option<String> {
+        self.document_text(settings_uri)
+            .or_else(|| fs::read_to_string(zed_paths::settings_file()).ok())
+    }
+
+    // file name and content of the open config file subject to sync
+    fn synced_document(&self, uri: &Url) -> Option<(String, String)> {
+        let path = ZedConfigFilePath::from_file_uri(uri, &self.file_filter()?).ok()?;
+        let file_name = path
+            .to_watched_path_buf()
+            .file_name()?
+            .to_string_lossy()
+            .into_owned();
+
+        Some((file_name, self.document_text(uri)?))
+    }
+
+    async fn config_hover(&self, uri: &Url, position: Position) -> Result<Option<String>> {
+        let Some((file_name, text)) = self.synced_document(uri) else {
+            return Ok(None);
+        };
+        let Some(key_path) = key_path_at(&text, offset_at(&text, position)) else {
+            return Ok(None);
+        };
+
+        let mut remote_files = self
+            .remote_snapshot
+            .load(self.sync_client()?.as_ref())
+            .await?;
+        let sync_state = SyncStateStore::new(&zed_paths::data_dir()).load()?;
+
+        Ok(Some(hover_markdown(
+            &key_path,
+            &text,
+            remote_files.files.remove(&file_name).as_deref(),
+            sync_state.file(&file_name),
+        )))
+    }
+
+    async fn config_code_lens(&self, uri: &Url) -> Result<Option<CodeLens>> {
+        let Some((file_name, text)) = self.synced_document(uri) else {
+            return Ok(None);
+        };
+
+        let remote_files = self
+            .remote_snapshot
+            .load(self.sync_client()?.as_ref())
+            .await?;
+        let sync_state = SyncStateStore::new(&zed_paths::data_dir()).load()?;
+        let file_state = sync_state.file(&file_name);
+        let status = sync_status(Some(&text), remote_files.files.get(&file_name), file_state);
+        let now_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| i64::try_from(duration.as_secs()).unwrap_or(i64::MAX))?;
+
+        Ok(sync_status_lens(
+            uri,
+            status,
+            file_state,
+            remote_files.revision.as_deref(),
+            now_timestamp,
+        ))
+    }
+
+    fn config_code_actions(
+        &self,
+        uri: &Url,
+        context_diagnostics: &[Diagnostic],
+    ) -> Vec<CodeAction> {
+        let Ok(settings_uri) = Url::from_file_path(zed_paths::settings_file()) else {
+            return Vec::new();
+        };
+        let Some(settings_text) = self.settings_text(&settings_uri) else {
+            return Vec::new();
+        };
+        let mut actions = Vec::new();
+
+        if *uri == settings_uri {
+            if let Ok(Some(new_text)) = insert_config_skeleton(&settings_text) {
+                actions.push(quick_fix(
+                    "Insert settings sync configuration".to_string(),
+                    settings_uri.clone(),
+                    &settings_text,
+                    new_text,
+                    diagnostics_with_code(context_diagnostics, NOT_CONFIGURED_CODE),
+                ));
+            }
+
+            if let Ok(github_token) = env::var(GITHUB_TOKEN_ENV_VAR)
+                && let Ok(Some(new_text)) = replace_masked_token(&settings_text, &github_token)
+            {
+                actions.push(quick_fix(
+                    format!("Replace [masked] with the token from {GITHUB_TOKEN_ENV_VAR}"),
+                    settings_uri.clone(),
+                    &settings_text,
+                    new_text,
+                    diagnostics_with_code(context_diagnostics, MASKED_TOKEN_CODE),
+                ));
+            }
+        }
+
+        if let Some(file_filter) = self.file_filter()
+            && let Ok(path) = ZedConfigFilePath::from_file_uri(uri, &file_filter)
+            && let Ok(relative_path) = path.as_ref().strip_prefix(zed_paths::config_dir())
+            && let Ok(Some(new_text)) =
+                exclude_from_sync(&settings_text, &relative_path.to_string_lossy())
+        {
+            actions.push(quick_fix(
+                format!("Exclude {} from sync", relative_path.display()),
+                settings_uri,
+                &settings_text,
+                new_text,
+                Vec::new(),
+            ));
+        }
+
+        actions
+    }
+
+    async fn publish_config_diagnostics(
+        &self,
+        uri: &Url,
+        text: Option<&str>,
+        version: Option<i32>,
+    ) {
+        if !is_settings_file(uri) {
+            return;
+        }
+
+        let diagnostics = text.map(config_diagnostics).unwrap_or_default();
+        debug!("Publishing {} diagnostics for {}", diagnostics.len(), uri);
+
+        self.lsp_client
+            .publish_diagnostics(uri.clone(), diagnostics, version)
+            .await;
+    }
+}
+
+// Zed sends `null` or `{}` for a server without settings
+fn has_settings(settings: &Value) -> bool {
+    !settings.is_null() && settings.as_object().is_none_or(|object| !object.is_empty())
+}
+
+fn is_settings_file(uri: &Url) -> bool {
+    uri.to_file_path()
+        .is_ok_and(|path| path.as_path() == zed_paths::settings_file().as_path())
 }
 
 #[tower_lsp::async_trait]
@@ -82,38 +438,14 @@ impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
         info!("Initializing Zed Settings Sync LSP...");
 
-        let options = params.initialization_options.ok_or_else(|| {
-            error!("initialization_options are missing from LSP server configuration");
-            tower_lsp::jsonrpc::Error::internal_error()
-        })?;
-        let config: Config = from_value(options).map_err(|err| {
-            error!("Failed to deserialize initialization_options: {}", err);
-            tower_lsp::jsonrpc::Error::internal_error()
-        })?;
-
-        let app_state = AppState::new(
-            config.gist_id().into(),
-            config.github_token().into(),
-            Arc::new(self.lsp_client.clone()),
-        )
-        .map_err(|err| {
-            error!("Failed to build the app state: {}", err);
-            tower_lsp::jsonrpc::Error::internal_error()
-        })?;
-
-        #[allow(clippy::expect_used)]
-        self.app_state
-            .set(Mutex::new(app_state))
-            .expect("AppState was already initialized");
-
-        #[allow(clippy::expect_used)]
-        self.app_state
-            .get()
-            .expect("App state should be initialized")
-            .lock()
-            .expect("Watched paths store mutex is poisoned")
-            .watched_paths
-            .start_watcher();
+        // a broken config is reported in the settings file, so the server keeps running without syncing
+        match self.build_app_state(params.initialization_options) {
+            Ok(app_state) => self.start(app_state).map_err(|err| {
+                error!("Failed to start syncing: {}", err);
+                tower_lsp::jsonrpc::Error::internal_error()
+            })?,
+            Err(err) => error!("{:#}, running without syncing", err),
+        }
 
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
@@ -124,6 +456,12 @@ impl LanguageServer for Backend {
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
                     TextDocumentSyncOptions {
                         open_close: Some(true),
+                        // full content is needed to keep the settings file diagnostics up to date
+                        change: Some(TextDocumentSyncKind::FULL),
+                        // the saved content is synced as is, without reading the file being written
+                        save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                            include_text: Some(true),
+                        })),
                         ..TextDocumentSyncOptions::default()
                     },
                 )),
@@ -131,6 +469,15 @@ impl LanguageServer for Backend {
                     file_operations: None,
                     workspace_folders: None,
                 }),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(false),
+                }),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: COMMANDS.map(str::to_string).to_vec(),
+                    ..ExecuteCommandOptions::default()
+                }),
                 ..Default::default()
             },
         })
@@ -138,6 +485,15 @@ impl LanguageServer for Backend {
 
     async fn initialized(&self, _: InitializedParams) {
         info!("Zed Settings Sync LSP server fully initialized and ready");
+
+        if self.app_state.get().is_none()
+            && let Err(err) = self.reload_config(Value::Null).await
+        {
+            debug!("No usable config in the server settings: {:#}", err);
+            self.lsp_client
+                .show_message(MessageType::WARNING, NOT_CONFIGURED_MESSAGE.to_string())
+                .await;
+        }
     }
 
     #[instrument(skip(self))]
@@ -147,11 +503,172 @@ impl LanguageServer for Backend {
         Ok(())
     }
 
+    #[instrument(skip(self, params))]
+    async fn execute_command(&self, params: ExecuteCommandParams) -> LspResult<Option<Value>> {
+        debug!("Executing command: {}", params.command);
+
+        let command =
+            Command::parse(&params, &self.file_filter().unwrap_or_default()).map_err(|err| {
+                error!("Invalid command: {}", err);
+                tower_lsp::jsonrpc::Error::invalid_params(err.to_string())
+            })?;
+
+        let changes_files = matches!(
+            command,
+            Command::SyncFile(_) | Command::SyncAll | Command::Pull
+        );
+
+        // not holding the app state lock while syncing
+        let result = match (command, self.sync_client()) {
+            (Command::ReloadConfig, _) => self.reload_config(Value::Null).await,
+            (_, Err(err)) => Err(err),
+            (Command::SyncFile(path), Ok(sync_client)) => {
+                commands::sync_file(sync_client.as_ref(), path).await
+            }
+            (Command::SyncAll, Ok(sync_client)) => {
+                commands::sync_all(
+                    sync_client.as_ref(),
+                    &self.file_filter().unwrap_or_default(),
+                )
+                .await
+            }
+            (Command::Pull, Ok(sync_client)) => self.pull_unwatched(sync_client.as_ref()).await,
+            (Command::ShowRemoteRevision, Ok(sync_client)) => {
+                commands::show_remote_revision(sync_client.as_ref()).await
+            }
+        };
+
+        match result {
+            Ok(message) => {
+                if changes_files {
+                    self.refresh_sync_status().await;
+                }
+                self.lsp_client
+                    .show_message(MessageType::INFO, message)
+                    .await;
+            }
+            Err(err) => {
+                error!("Command {} failed: {}", params.command, err);
+                self.lsp_client
+                    .show_message(MessageType::ERROR, err.to_string())
+                    .await;
+            }
+        }
+
+        Ok(None)
+    }
+
+    #[instrument(skip(self, params))]
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        debug!("Configuration changed");
+
+        match self.reload_config(params.settings).await {
+            Ok(message) => info!("{}", message),
+            Err(err) => {
+                error!("Failed to reload the config: {:#}", err);
+                self.lsp_client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("Settings sync config wasn't reloaded, the previous one is kept: {err:#}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    #[instrument(skip(self, params))]
+    async fn hover(&self, params: HoverParams) -> LspResult<Option<Hover>> {
+        let position_params = params.text_document_position_params;
+
+        match self
+            .config_hover(&position_params.text_document.uri, position_params.position)
+            .await
+        {
+            Ok(markdown) => Ok(markdown.map(|value| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            })),
+            Err(err) => {
+                error!("Failed to build the hover: {:#}", err);
+                Ok(None)
+            }
+        }
+    }
+
+    #[instrument(skip(self, params))]
+    async fn code_lens(&self, params: CodeLensParams) -> LspResult<Option<Vec<CodeLens>>> {
+        match self.config_code_lens(&params.text_document.uri).await {
+            Ok(lens) => Ok(lens.map(|lens| vec![lens])),
+            Err(err) => {
+                error!("Failed to build the code lens: {:#}", err);
+                Ok(None)
+            }
+        }
+    }
+
+    #[instrument(skip(self, params))]
+    async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
+        let actions =
+            self.config_code_actions(&params.text_document.uri, &params.context.diagnostics);
+        debug!(
+            "Offering {} code actions for {}",
+            actions.len(),
+            params.text_document.uri
+        );
+
+        if actions.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            actions
+                .into_iter()
+                .map(CodeActionOrCommand::CodeAction)
+                .collect(),
+        ))
+    }
+
+    #[instrument(skip(self, params))]
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // with full sync, the last change holds the whole content
+        let text = params
+            .content_changes
+            .last()
+            .map(|change| change.text.as_str());
+        if let Some(text) = text {
+            self.set_document_text(&params.text_document.uri, Some(text));
+        }
+
+        self.publish_config_diagnostics(
+            &params.text_document.uri,
+            text,
+            Some(params.text_document.version),
+        )
+        .await;
+    }
+
     #[instrument(skip(self, params))]
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         debug!("Document opened: {}", params.text_document.uri);
 
-        match ZedConfigFilePath::from_file_uri(&params.text_document.uri) {
+        self.set_document_text(&params.text_document.uri, Some(&params.text_document.text));
+
+        self.publish_config_diagnostics(
+            &params.text_document.uri,
+            Some(&params.text_document.text),
+            Some(params.text_document.version),
+        )
+        .await;
+
+        let Some(file_filter) = self.file_filter() else {
+            debug!("Settings sync is not configured, not watching the document");
+            return;
+        };
+
+        match ZedConfigFilePath::from_file_uri(&params.text_document.uri, &file_filter) {
             Ok(path) => {
                 let path_to_watch = path.to_watched_path_buf();
                 // TODO: expose sync_client in app state and sync file explicitly after opening
@@ -166,17 +683,78 @@ impl LanguageServer for Backend {
                     params.text_document.uri
                 );
             }
+            Err(ZedConfigPathError::ExcludedFromSync) => {
+                debug!(
+                    "Zed config file is excluded from sync, skipping: {}",
+                    params.text_document.uri
+                );
+            }
             Err(ZedConfigPathError::WrongFileUriFormat) => {
                 error!("Wrong file uri format: {}", params.text_document.uri);
             }
         }
     }
 
+    #[instrument(skip(self, params))]
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+        let Some(text) = params.text else {
+            debug!(
+                "No text in the saved document, leaving it to the file watcher: {}",
+                uri
+            );
+            return;
+        };
+        let Some(file_filter) = self.file_filter() else {
+            return;
+        };
+        let Ok(path) = ZedConfigFilePath::from_file_uri(&uri, &file_filter) else {
+            debug!("Not a synced Zed config file, skipping: {}", uri);
+            return;
+        };
+
+        let data = match LocalFileData::new(path.to_watched_path_buf(), text) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Could not process the saved file: {}", err);
+                return;
+            }
+        };
+        let (sync_client, reporter) = match self.save_sync_handles() {
+            Ok(Some(handles)) => handles,
+            Ok(None) => return,
+            Err(err) => {
+                error!("Could not sync the saved file: {}", err);
+                return;
+            }
+        };
+
+        let progress_token = reporter.begin(&data.filename).await;
+        let result = sync_client.sync_file(data).await;
+        if let Err(err) = &result {
+            error!("Could not sync file: {err}");
+        }
+        reporter.end(progress_token, &result).await;
+        if result.is_ok() {
+            self.refresh_sync_status().await;
+        }
+    }
+
     #[instrument(skip(self, params))]
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         debug!("Document closed: {}", params.text_document.uri);
 
-        match ZedConfigFilePath::from_file_uri(&params.text_document.uri) {
+        self.set_document_text(&params.text_document.uri, None);
+
+        // the diagnostics of a closed file are cleared
+        self.publish_config_diagnostics(&params.text_document.uri, None, None)
+            .await;
+
+        let Some(file_filter) = self.file_filter() else {
+            return;
+        };
+
+        match ZedConfigFilePath::from_file_uri(&params.text_document.uri, &file_filter) {
             Ok(path) => {
                 let path_to_watch = path.to_watched_path_buf();
                 if let Err(err) = self.unwatch_path(&path_to_watch) {
@@ -189,6 +767,12 @@ impl LanguageServer for Backend {
                     params.text_document.uri
                 );
             }
+            Err(ZedConfigPathError::ExcludedFromSync) => {
+                debug!(
+                    "Zed config file is excluded from sync, skipping: {}",
+                    params.text_document.uri
+                );
+            }
             Err(ZedConfigPathError::WrongFileUriFormat) => {
                 error!("Wrong file uri format: {}", params.text_document.uri);
             }
@@ -200,23 +784,53 @@ impl LanguageServer for Backend {
 mod tests {
     #![allow(clippy::unwrap_used)]
 
+    use std::fs;
     use std::path::Path;
     use std::path::PathBuf;
 
     use anyhow::Result;
     use anyhow::anyhow;
+    use common::config::SyncTrigger;
+    use common::sync::{GistAccess, LocalFileData, MockGithubClient, SyncStateStore};
     use mockall::{Sequence, predicate};
-    use tower_lsp::{LanguageServer, lsp_types::InitializeParams};
+    use test_support::zed_paths;
+    use tower_lsp::{
+        LanguageServer,
+        lsp_types::{
+            CodeActionContext, CodeActionOrCommand, CodeActionParams, CodeLensParams,
+            DidChangeConfigurationParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+            ExecuteCommandParams, HoverContents, HoverParams, InitializeParams, MessageType,
+            NumberOrString, PartialResultParams, Position, Range, TextDocumentIdentifier,
+            TextDocumentItem, TextDocumentPositionParams, Url, WorkDoneProgressParams,
+        },
+    };
     use zed_extension_api::serde_json::{Value, json};
 
     use crate::{backend::Backend, mocks::MockLspClient, watching::MockPathStore};
 
     async fn init_lsp_backend(initialization_options: Option<Value>) -> Result<Backend> {
-        let mut mock_lsp_client = MockLspClient::default();
+        init_lsp_backend_with_clients(
+            initialization_options,
+            MockLspClient::default(),
+            MockGithubClient::default,
+        )
+        .await
+    }
+
+    async fn init_lsp_backend_with_clients(
+        initialization_options: Option<Value>,
+        mut mock_lsp_client: MockLspClient,
+        build_sync_client: impl Fn() -> MockGithubClient + Send + 'static,
+    ) -> Result<Backend> {
         mock_lsp_client
             .expect_clone()
             .returning(MockLspClient::default);
 
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx
+            .expect()
+            .returning(move |_, _, _| Ok(build_sync_client()));
+
         let backend = Backend::new(mock_lsp_client);
         let initialize_params = InitializeParams {
             initialization_options,
@@ -238,7 +852,7 @@ mod tests {
     #[tokio::test]
     async fn test_initialize_success() -> Result<()> {
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
+        ctx.expect().returning(|_, _, _, _, _| {
             let mut mock_path_store = MockPathStore::default();
             mock_path_store.expect_start_watcher().returning(|| ());
             Ok(mock_path_store)
@@ -250,27 +864,21 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_initialize_failure_missing_initialization_options() -> Result<()> {
+    async fn test_initialize_without_sync_missing_initialization_options() -> Result<()> {
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
-            let mut mock_path_store = MockPathStore::default();
-            mock_path_store.expect_start_watcher().returning(|| ());
-            Ok(mock_path_store)
-        });
+        ctx.expect().never();
+
+        let backend = init_lsp_backend(None).await?;
 
-        assert!(init_lsp_backend(None).await.is_err());
+        assert!(backend.app_state.get().is_none());
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_initialize_failure_invalid_initialization_options() -> Result<()> {
+    async fn test_initialize_without_sync_invalid_initialization_options() -> Result<()> {
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
-            let mut mock_path_store = MockPathStore::default();
-            mock_path_store.expect_start_watcher().returning(|| ());
-            Ok(mock_path_store)
-        });
+        ctx.expect().never();
 
         let test_cases = [
             json!({
@@ -290,10 +898,17 @@ mod tests {
                "github_token": "5678",
                "random_prop": "val"
             }),
+            json!({
+               "github_token": "tok",
+               "gist_id": "1234",
+               "exclude": ["[invalid"]
+            }),
         ];
 
         for test in test_cases {
-            assert!(init_lsp_backend(Some(test)).await.is_err());
+            let backend = init_lsp_backend(Some(test)).await?;
+
+            assert!(backend.app_state.get().is_none());
         }
 
         Ok(())
@@ -304,7 +919,7 @@ mod tests {
         let path = "/path/to/watch";
 
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
+        ctx.expect().returning(|_, _, _, _, _| {
             let mut seq = Sequence::new();
             let mut mock_path_store = MockPathStore::default();
             mock_path_store
@@ -331,7 +946,7 @@ mod tests {
         let path = "/path/to/watch";
 
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
+        ctx.expect().returning(|_, _, _, _, _| {
             let mut seq = Sequence::new();
             let mut mock_path_store = MockPathStore::default();
             mock_path_store
@@ -364,7 +979,7 @@ mod tests {
         let path = "/path/to/watch";
 
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
+        ctx.expect().returning(|_, _, _, _, _| {
             let mut seq = Sequence::new();
             let mut mock_path_store = MockPathStore::default();
             mock_path_store
@@ -391,7 +1006,7 @@ mod tests {
         let path = "/path/to/watch";
 
         let ctx = MockPathStore::new_context();
-        ctx.expect().returning(|_, _| {
+        ctx.expect().returning(|_, _, _, _, _| {
             let mut seq = Sequence::new();
             let mut mock_path_store = MockPathStore::default();
             mock_path_store
@@ -418,4 +1033,789 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_execute_command_shows_remote_revision() -> Result<()> {
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            Ok(mock_path_store)
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client
+            .expect_show_message()
+            .with(
+                predicate::eq(MessageType::INFO),
+                predicate::eq("Remote revision: abc123".to_owned()),
+            )
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            || {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_remote_revision()
+                    .returning(|| Ok(Some("abc123".to_string())));
+                mock_sync_client
+            },
+        )
+        .await?;
+
+        let result = backend
+            .execute_command(ExecuteCommandParams {
+                command: "settings-sync.showRemoteRevision".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert!(result.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_refreshes_sync_status_after_pull() -> Result<()> {
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            mock_path_store.expect_watched_paths().returning(Vec::new);
+            Ok(mock_path_store)
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client
+            .expect_show_message()
+            .with(
+                predicate::eq(MessageType::INFO),
+                predicate::eq("Pulled 0 file(s)".to_owned()),
+            )
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        // loaded by the pull, and for the remote snapshot before and after it
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            || {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_remote_revision()
+                    .times(3)
+                    .returning(|| Ok(Some("abc123".to_string())));
+                mock_sync_client
+                    .expect_load_files()
+                    .times(3)
+                    .returning(|| Ok(Box::new(std::iter::empty())));
+                mock_sync_client
+            },
+        )
+        .await?;
+
+        let sync_client = backend.sync_client()?;
+        backend.remote_snapshot.load(sync_client.as_ref()).await?;
+
+        backend
+            .execute_command(ExecuteCommandParams {
+                command: "settings-sync.pull".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        backend.remote_snapshot.load(sync_client.as_ref()).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_pull_does_not_sync_pulled_files_back() -> Result<()> {
+        let keymap_file = zed_paths::config_dir().join("keymap.json");
+
+        // the pulled file is written while it isn't watched
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning({
+            let keymap_file = keymap_file.clone();
+            move |_, _, _, _, _| {
+                let mut seq = Sequence::new();
+                let mut mock_path_store = MockPathStore::default();
+                mock_path_store.expect_start_watcher().returning(|| ());
+                mock_path_store.expect_watched_paths().returning({
+                    let keymap_file = keymap_file.clone();
+                    move || vec![keymap_file.clone()]
+                });
+                mock_path_store
+                    .expect_unwatch()
+                    .with(predicate::eq(keymap_file.clone()))
+                    .once()
+                    .in_sequence(&mut seq)
+                    .returning(|_| Ok(()));
+                mock_path_store
+                    .expect_watch()
+                    .with(predicate::eq(keymap_file.clone()))
+                    .once()
+                    .in_sequence(&mut seq)
+                    .returning(|_| Ok(()));
+                Ok(mock_path_store)
+            }
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client
+            .expect_show_message()
+            .with(
+                predicate::eq(MessageType::INFO),
+                predicate::eq("Pulled 1 file(s)".to_owned()),
+            )
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            || {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_remote_revision()
+                    .returning(|| Ok(Some("abc123".to_string())));
+                mock_sync_client.expect_load_files().returning(|| {
+                    Ok(Box::new(
+                        [Ok(("keymap.json".to_string(), "[]".to_string()))].into_iter(),
+                    ))
+                });
+                mock_sync_client.expect_sync_file().never();
+                mock_sync_client
+            },
+        )
+        .await?;
+
+        backend
+            .execute_command(ExecuteCommandParams {
+                command: "settings-sync.pull".to_string(),
+                ..Default::default()
+            })
+            .await?;
+
+        assert_eq!(fs::read_to_string(&keymap_file)?, "[]");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_change_configuration_swaps_sync_client() -> Result<()> {
+        let ctx = MockPathStore::new_context();
+        ctx.expect().once().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            mock_path_store
+                .expect_set_sync_client()
+                .once()
+                .return_const(());
+            mock_path_store.expect_set_sync_trigger().return_const(());
+            mock_path_store.expect_watched_paths().returning(Vec::new);
+            Ok(mock_path_store)
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client.expect_configuration().returning(|_items| {
+            Box::pin(async {
+                Ok(vec![json!({
+                    "github_token": "gho_my-new-token",
+                    "gist_id": "feedfacefeedfacefeedfacefeedface"
+                })])
+            })
+        });
+        mock_lsp_client.expect_show_message().never();
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            MockGithubClient::default,
+        )
+        .await?;
+
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx
+            .expect()
+            .with(
+                predicate::eq("feedfacefeedfacefeedfacefeedface".to_string()),
+                predicate::eq("gho_my-new-token".to_string()),
+                predicate::always(),
+            )
+            .once()
+            .returning(|_, _, _| {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_gist_access()
+                    .returning(|| Ok(GistAccess::Writable));
+                Ok(mock_sync_client)
+            });
+
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: Value::Null,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_change_configuration_keeps_sync_client_on_invalid_config() -> Result<()> {
+        let ctx = MockPathStore::new_context();
+        ctx.expect().once().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            mock_path_store.expect_set_sync_client().never();
+            Ok(mock_path_store)
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client.expect_configuration().returning(|_items| {
+            Box::pin(async { Err(tower_lsp::jsonrpc::Error::method_not_found()) })
+        });
+        mock_lsp_client
+            .expect_show_message()
+            .withf(|msg_type, msg| {
+                *msg_type == MessageType::ERROR
+                    && msg.starts_with("Settings sync config wasn't reloaded")
+                    && msg.ends_with("Gist feedfacefeedfacefeedfacefeedface not found")
+            })
+            .once()
+            .returning(|_msg_type, _msg| Box::pin(async {}));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            MockGithubClient::default,
+        )
+        .await?;
+
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx.expect().returning(|_, _, _| {
+            let mut mock_sync_client = MockGithubClient::default();
+            mock_sync_client
+                .expect_gist_access()
+                .returning(|| Ok(GistAccess::NotFound));
+            Ok(mock_sync_client)
+        });
+
+        // the pushed settings are used when the client doesn't support pulling them
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: json!({
+                    "github_token": "gho_my-new-token",
+                    "gist_id": "feedfacefeedfacefeedfacefeedface"
+                }),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_change_configuration_unwatches_newly_excluded_paths() -> Result<()> {
+        let debug_file = zed_paths::config_dir().join("debug.json");
+        let keymap_file = zed_paths::config_dir().join("keymap.json");
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().once().returning({
+            let debug_file = debug_file.clone();
+            let keymap_file = keymap_file.clone();
+            move |_, _, _, _, _| {
+                let mut mock_path_store = MockPathStore::default();
+                mock_path_store.expect_start_watcher().returning(|| ());
+                mock_path_store.expect_set_sync_client().return_const(());
+                mock_path_store.expect_set_sync_trigger().return_const(());
+                mock_path_store.expect_watched_paths().returning({
+                    let debug_file = debug_file.clone();
+                    let keymap_file = keymap_file.clone();
+                    move || vec![debug_file.clone(), keymap_file.clone()]
+                });
+                mock_path_store
+                    .expect_unwatch()
+                    .with(predicate::eq(debug_file.clone()))
+                    .once()
+                    .returning(|_| Ok(()));
+                Ok(mock_path_store)
+            }
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client.expect_configuration().returning(|_items| {
+            Box::pin(async {
+                Ok(vec![json!({
+                    "github_token": "gho_my-shiny-token",
+                    "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef",
+                    "exclude": ["debug.json"]
+                })])
+            })
+        });
+        mock_lsp_client.expect_show_message().never();
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            MockGithubClient::default,
+        )
+        .await?;
+
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx.expect().returning(|_, _, _| {
+            let mut mock_sync_client = MockGithubClient::default();
+            mock_sync_client
+                .expect_gist_access()
+                .returning(|| Ok(GistAccess::Writable));
+            Ok(mock_sync_client)
+        });
+
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: Value::Null,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_change_configuration_watches_documents_opened_before_config() -> Result<()> {
+        let keymap_file = zed_paths::config_dir().join("keymap.json");
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().once().returning({
+            let keymap_file = keymap_file.clone();
+            move |_, _, _, _, _| {
+                let mut mock_path_store = MockPathStore::default();
+                mock_path_store.expect_start_watcher().returning(|| ());
+                mock_path_store.expect_watched_paths().returning(Vec::new);
+                mock_path_store
+                    .expect_watch()
+                    .with(predicate::eq(keymap_file.clone()))
+                    .once()
+                    .returning(|_| Ok(()));
+                Ok(mock_path_store)
+            }
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client.expect_configuration().returning(|_items| {
+            Box::pin(async {
+                Ok(vec![json!({
+                    "github_token": "gho_my-shiny-token",
+                    "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+                })])
+            })
+        });
+        mock_lsp_client.expect_show_message().never();
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        let backend =
+            init_lsp_backend_with_clients(None, mock_lsp_client, MockGithubClient::default).await?;
+
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    Url::from_file_path(&keymap_file).unwrap(),
+                    "json".to_string(),
+                    1,
+                    "[]".to_string(),
+                ),
+            })
+            .await;
+
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx.expect().returning(|_, _, _| {
+            let mut mock_sync_client = MockGithubClient::default();
+            mock_sync_client
+                .expect_gist_access()
+                .returning(|| Ok(GistAccess::Writable));
+            Ok(mock_sync_client)
+        });
+
+        backend
+            .did_change_configuration(DidChangeConfigurationParams {
+                settings: Value::Null,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_failure_unknown_command() -> Result<()> {
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            Ok(mock_path_store)
+        });
+
+        let backend = init_lsp_backend_default().await?;
+
+        assert!(
+            backend
+                .execute_command(ExecuteCommandParams {
+                    command: "settings-sync.unknown".to_string(),
+                    ..Default::default()
+                })
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_open_publishes_settings_file_diagnostics() -> Result<()> {
+        let settings_file = zed_paths::settings_file();
+        let uri = Url::from_file_path(&settings_file).unwrap();
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning({
+            let settings_file = settings_file.clone();
+            move |_, _, _, _, _| {
+                let mut mock_path_store = MockPathStore::default();
+                mock_path_store.expect_start_watcher().returning(|| ());
+                mock_path_store
+                    .expect_watch()
+                    .with(predicate::eq(settings_file.clone()))
+                    .returning(|_| Ok(()));
+                Ok(mock_path_store)
+            }
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client
+            .expect_publish_diagnostics()
+            .withf({
+                let uri = uri.clone();
+                move |published_uri, diagnostics, version| {
+                    *published_uri == uri
+                        && diagnostics.len() == 1
+                        && diagnostics[0].code
+                            == Some(NumberOrString::String("masked-token".to_string()))
+                        && *version == Some(1)
+                }
+            })
+            .return_once(|_uri, _diagnostics, _version| Box::pin(async {}));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            MockGithubClient::default,
+        )
+        .await?;
+
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri,
+                    "json".to_string(),
+                    1,
+                    r#"{ "lsp": { "settings-sync": { "initialization_options": { "gist_id": "1234", "github_token": "[masked]" } } } }"#
+                        .to_string(),
+                ),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_did_save_syncs_saved_text_in_save_mode() -> Result<()> {
+        let keymap_file = zed_paths::config_dir().join("keymap.json");
+        let text = r#"[{ "bindings": { "cmd-,": "zed::OpenSettingsFile" } }]"#;
+
+        // the watcher skips the saved documents by itself
+        let ctx = MockPathStore::new_context();
+        ctx.expect()
+            .withf(|_, _, sync_trigger, _, _| *sync_trigger == SyncTrigger::Save)
+            .returning(|_, _, _, _, _| {
+                let mut mock_path_store = MockPathStore::default();
+                mock_path_store.expect_start_watcher().returning(|| ());
+                Ok(mock_path_store)
+            });
+
+        // the reporter gets a clone of the client
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client.expect_clone().returning(|| {
+            let mut reporter_lsp_client = MockLspClient::default();
+            reporter_lsp_client
+                .expect_create_work_done_progress()
+                .returning(|_token| Box::pin(async { Ok(()) }));
+            reporter_lsp_client
+                .expect_send_work_done_progress()
+                .returning(|_token, _progress| Box::pin(async {}));
+            reporter_lsp_client
+                .expect_show_message()
+                .with(
+                    predicate::eq(MessageType::INFO),
+                    predicate::eq("Successfully synced".to_owned()),
+                )
+                .returning(|_msg_type, _msg| Box::pin(async {}));
+            reporter_lsp_client
+        });
+
+        let sync_client_ctx = MockGithubClient::new_context();
+        sync_client_ctx.expect().returning({
+            let keymap_file = keymap_file.clone();
+            move |_, _, _| {
+                let file_data = LocalFileData::new(keymap_file.clone(), text.to_string()).unwrap();
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_sync_file()
+                    .with(predicate::eq(file_data))
+                    .once()
+                    .return_once(|_| Ok(()));
+                Ok(mock_sync_client)
+            }
+        });
+
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+
+        let backend = Backend::new(mock_lsp_client);
+        backend
+            .initialize(InitializeParams {
+                initialization_options: Some(json!({
+                    "github_token": "gho_my-shiny-token",
+                    "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef",
+                    "sync_trigger": "save"
+                })),
+                ..Default::default()
+            })
+            .await?;
+
+        backend
+            .did_save(DidSaveTextDocumentParams {
+                text_document: TextDocumentIdentifier::new(
+                    Url::from_file_path(&keymap_file).unwrap(),
+                ),
+                text: Some(text.to_string()),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hover_shows_remote_value_of_key() -> Result<()> {
+        let uri = Url::from_file_path(zed_paths::settings_file()).unwrap();
+        let text = r#"{ "theme": "One Dark" }"#;
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            Ok(mock_path_store)
+        });
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            MockLspClient::default(),
+            || {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_remote_revision()
+                    .returning(|| Ok(Some("abc123".to_string())));
+                mock_sync_client.expect_load_files().once().returning(|| {
+                    Ok(Box::new(
+                        [Ok((
+                            "settings.json".to_string(),
+                            r#"{ "theme": "Ayu Dark" }"#.to_string(),
+                        ))]
+                        .into_iter(),
+                    ))
+                });
+                mock_sync_client
+            },
+        )
+        .await?;
+        backend.set_document_text(&uri, Some(text));
+
+        let hover = backend
+            .hover(HoverParams {
+                text_document_position_params: TextDocumentPositionParams::new(
+                    TextDocumentIdentifier::new(uri),
+                    Position::new(0, 4),
+                ),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await?
+            .unwrap();
+
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("Unexpected hover contents: {:?}", hover.contents);
+        };
+        assert!(
+            markup
+                .value
+                .contains("the remote value is:\n```json\n\"Ayu Dark\"\n```")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_code_lens_offers_pull_of_newer_remote_changes() -> Result<()> {
+        let keymap_file = zed_paths::config_dir().join("keymap.json");
+        let uri = Url::from_file_path(&keymap_file).unwrap();
+        let synced_text = r#"[{ "bindings": { "cmd-k": "workspace::Save" } }]"#;
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            Ok(mock_path_store)
+        });
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            MockLspClient::default(),
+            || {
+                let mut mock_sync_client = MockGithubClient::default();
+                mock_sync_client
+                    .expect_remote_revision()
+                    .returning(|| Ok(Some("abc123".to_string())));
+                mock_sync_client.expect_load_files().returning(|| {
+                    Ok(Box::new(
+                        [Ok((
+                            "keymap.json".to_string(),
+                            r#"[{ "bindings": { "cmd-k": "workspace::SaveAll" } }]"#.to_string(),
+                        ))]
+                        .into_iter(),
+                    ))
+                });
+                mock_sync_client
+            },
+        )
+        .await?;
+        SyncStateStore::new(&zed_paths::data_dir()).record_synced("keymap.json", synced_text)?;
+        backend.set_document_text(&uri, Some(synced_text));
+
+        let lenses = backend
+            .code_lens(CodeLensParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await?
+            .unwrap();
+
+        let command = lenses[0].command.clone().unwrap();
+        assert_eq!(command.title, "Remote has newer changes — Pull");
+        assert_eq!(command.command, "settings-sync.pull");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_code_action_inserts_config_into_open_settings_file() -> Result<()> {
+        let uri = Url::from_file_path(zed_paths::settings_file()).unwrap();
+
+        let ctx = MockPathStore::new_context();
+        ctx.expect().returning(|_, _, _, _, _| {
+            let mut mock_path_store = MockPathStore::default();
+            mock_path_store.expect_start_watcher().returning(|| ());
+            mock_path_store.expect_watch().returning(|_| Ok(()));
+            Ok(mock_path_store)
+        });
+
+        let mut mock_lsp_client = MockLspClient::default();
+        mock_lsp_client
+            .expect_publish_diagnostics()
+            .returning(|_uri, _diagnostics, _version| Box::pin(async {}));
+
+        let backend = init_lsp_backend_with_clients(
+            Some(json!({
+                "github_token": "gho_my-shiny-token",
+                "gist_id": "deadbeefdeadbeefdeadbeefdeadbeef"
+            })),
+            mock_lsp_client,
+            MockGithubClient::default,
+        )
+        .await?;
+
+        backend
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "json".to_string(),
+                    1,
+                    r#"{ "theme": "One Dark" }"#.to_string(),
+                ),
+            })
+            .await;
+
+        let actions = backend
+            .code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier::new(uri),
+                range: Range::default(),
+                context: CodeActionContext::default(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            })
+            .await?
+            .unwrap();
+
+        let titles: Vec<_> = actions
+            .iter()
+            .map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.title.as_str(),
+                CodeActionOrCommand::Command(command) => command.title.as_str(),
+            })
+            .collect();
+        assert_eq!(titles, ["Insert settings sync configuration"]);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use common::sync::MASKED_GITHUB_TOKEN;
+use jsonc_parser::{
+    ParseOptions,
+    cst::{CstInputValue, CstObject, CstRootNode},
+    errors::ParseError,
+};
+use tower_lsp::lsp_types::{
+    CodeAction, CodeActionKind, Diagnostic, NumberOrString, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::diagnostics::position_at;
+
+/// Settings file content with the `settings-sync` options skeleton added, `None` if the options exist
+pub fn insert_config_skeleton(settings_text: &str) -> Result<Option<String>, ParseError> {
+    let root = CstRootNode::parse(settings_text, &ParseOptions::default())?;
+    let settings_sync = root
+        .object_value_or_set()
+        .object_value_or_set("lsp")
+        .object_value_or_set("settings-sync");
+
+    if settings_sync.get("initialization_options").is_some() {
+        return Ok(None);
+    }
+    settings_sync.append(
+        "initialization_options",
+        CstInputValue::Object(vec![
+            ("gist_id".to_string(), CstInputValue::String(String::new())),
+            (
+                "github_token".to_string(),
+                CstInputValue::String(String::new()),
+            ),
+        ]),
+    );
+
+    Ok(Some(root.to_string()))
+}
+
+/// Settings file content with the masked Github token replaced, `None` if the token isn't masked
+pub fn replace_masked_token(
+    settings_text: &str,
+    github_token: &str,
+) -> Result<Option<String>, ParseError> {
+    let root = CstRootNode::parse(settings_text, &ParseOptions::default())?;
+    let Some(token_prop) =
+        initialization_options(&root).and_then(|options| options.get("github_token"))
+    else {
+        return Ok(None);
+    };
+
+    let is_masked = token_prop
+        .value()
+        .and_then(|value| value.as_string_lit())
+        .and_then(|value| value.decoded_value().ok())
+        .is_some_and(|value| value == MASKED_GITHUB_TOKEN);
+    if !is_masked {
+        return Ok(None);
+    }
+    token_prop.set_value(CstInputValue::String(github_token.to_string()));
+
+    Ok(Some(root.to_string()))
+}
+
+/// Settings file content with the file added to the sync exclusions, `None` if it's excluded already
+pub fn exclude_from_sync(
+    settings_text: &str,
+    file_name: &str,
+) -> Result<Option<String>, ParseError> {
+    let root = CstRootNode::parse(settings_text, &ParseOptions::default())?;
+    let Some(options) = initialization_options(&root) else {
+        return Ok(None);
+    };
+
+    let exclude = options.array_value_or_set("exclude");
+    let is_excluded = exclude.elements().into_iter().any(|element| {
+        element
+            .as_string_lit()
+            .and_then(|value| value.decoded_value().ok())
+            .is_some_and(|value| value == file_name)
+    });
+    if is_excluded {
+        return Ok(None);
+    }
+    exclude.append(CstInputValue::String(file_name.to_string()));
+
+    Ok(Some(root.to_string()))
+}
+
+/// Quick fix replacing the whole document, the unchanged parts keep their formatting as they come from the CST
+pub fn quick_fix(
+    title: String,
+    uri: Url,
+    old_text: &str,
+    new_text: String,
+    diagnostics: Vec<Diagnostic>,
+) -> CodeAction {
+    let edit = TextEdit::new(
+        Range::new(
+            position_at(old_text, 0),
+            position_at(old_text, old_text.len()),
+        ),
+        new_text,
+    );
+
+    CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        is_preferred: Some(!diagnostics.is_empty()),
+        diagnostics: (!diagnostics.is_empty()).then_some(diagnostics),
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri, vec![edit])])),
+            ..WorkspaceEdit::default()
+        }),
+        ..CodeAction::default()
+    }
+}
+
+/// Diagnostics of the request context having the given code
+pub fn diagnostics_with_code(diagnostics: &[Diagnostic], code: &str) -> Vec<Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            matches!(&diagnostic.code, Some(NumberOrString::String(value)) if value == code)
+        })
+        .cloned()
+        .collect()
+}
+
+fn initialization_options(root: &CstRootNode) -> Option<CstObject> {
+    root.object_value()?
+        .object_value("lsp")?
+        .object_value("settings-sync")?
+        .object_value("initialization_options")
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn test_config_skeleton_is_inserted_keeping_formatting() {
+        let text = r#"{
+  // my theme
+  "theme": "One Dark",
+  "lsp": {
+    "rust-analyzer": {}
+  }
+}"#;
+
+        let new_text = insert_config_skeleton(text).unwrap().unwrap();
+
+        assert!(new_text.starts_with("{\n  // my theme\n  \"theme\": \"One Dark\","));
+        assert!(new_text.contains(r#""gist_id": """#));
+        assert!(new_text.contains(r#""github_token": """#));
+        assert_eq!(insert_config_skeleton(&new_text).unwrap(), None);
+    }
+
+    #[test]
+    fn test_masked_token_is_replaced() {
+        let text = r#"{ "lsp": { "settings-sync": { "initialization_options": { "gist_id": "1234", "github_token": "[masked]" } } } }"#;
+
+        assert_eq!(
+            replace_masked_token(text, "gho_token").unwrap().unwrap(),
+            r#"{ "lsp": { "settings-sync": { "initialization_options": { "gist_id": "1234", "github_token": "gho_token" } } } }"#
+        );
+        assert_eq!(
+            replace_masked_token(&text.replace("[masked]", "gho_other"), "gho_token").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_file_is_excluded_once() {
+        let text = r#"{ "lsp": { "settings-sync": { "initialization_options": { "gist_id": "1234", "github_token": "gho_token", "exclude": ["tasks.json"] } } } }"#;
+
+        let new_text = exclude_from_sync(text, "keymap.json").unwrap().unwrap();
+
+        assert!(new_text.contains(r#""exclude": ["tasks.json", "keymap.json"]"#));
+        assert_eq!(exclude_from_sync(&new_text, "keymap.json").unwrap(), None);
+    }
+}
@@ -0,0 +1,121 @@
+use common::sync::{FileSyncState, SyncStatus, short_id};
+use tower_lsp::lsp_types::{CodeLens, Command as LspCommand, Position, Range, Url};
+use zed_extension_api::serde_json::Value;
+
+use crate::commands::{PULL_COMMAND, SYNC_FILE_COMMAND};
+
+/// Lens at the top of a synced config file showing its sync status, `None` if there is nothing to show
+pub fn sync_status_lens(
+    uri: &Url,
+    status: SyncStatus,
+    file_state: Option<&FileSyncState>,
+    remote_revision: Option<&str>,
+    now_timestamp: i64,
+) -> Option<CodeLens> {
+    let sync_file = |title: String| LspCommand {
+        title,
+        command: SYNC_FILE_COMMAND.to_string(),
+        arguments: Some(vec![Value::String(uri.to_string())]),
+    };
+
+    let command = match status {
+        SyncStatus::InSync => {
+            let synced = file_state.map_or_else(
+                || "In sync".to_string(),
+                |state| {
+                    format!(
+                        "Synced {}",
+                        time_ago(now_timestamp - state.synced_at.timestamp())
+                    )
+                },
+            );
+            let title = match remote_revision {
+                Some(revision) => format!("{synced} · rev {}", short_id(revision)),
+                None => synced,
+            };
+            sync_file(title)
+        }
+        SyncStatus::LocallyModified => sync_file("Local changes aren't synced — Sync".to_string()),
+        SyncStatus::RemotelyModified => LspCommand {
+            title: "Remote has newer changes — Pull".to_string(),
+            command: PULL_COMMAND.to_string(),
+            arguments: None,
+        },
+        SyncStatus::Diverged => {
+            sync_file("Changed both locally and remotely — Sync to overwrite remote".to_string())
+        }
+        SyncStatus::MissingRemotely => sync_file("Not in the Gist yet — Sync".to_string()),
+        SyncStatus::MissingLocally | SyncStatus::Error => return None,
+    };
+
+    Some(CodeLens {
+        range: Range::new(Position::new(0, 0), Position::new(0, 0)),
+        command: Some(command),
+        data: None,
+    })
+}
+
+fn time_ago(seconds: i64) -> String {
+    match seconds {
+        ..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn lens_title(lens: Option<CodeLens>) -> (String, String) {
+        let command = lens.unwrap().command.unwrap();
+        (command.title, command.command)
+    }
+
+    #[test]
+    fn test_in_sync_lens_shows_sync_time_and_revision() {
+        let uri = Url::parse("file:///home/user/.config/zed/settings.json").unwrap();
+        let file_state = FileSyncState {
+            content_hash: String::new(),
+            synced_at: "2026-01-02T03:04:05Z".parse().unwrap(),
+        };
+        let now_timestamp = file_state.synced_at.timestamp() + 150;
+
+        assert_eq!(
+            lens_title(sync_status_lens(
+                &uri,
+                SyncStatus::InSync,
+                Some(&file_state),
+                Some("abc123def456"),
+                now_timestamp
+            )),
+            (
+                "Synced 2 min ago · rev abc123d".to_string(),
+                SYNC_FILE_COMMAND.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_remotely_modified_lens_pulls() {
+        let uri = Url::parse("file:///home/user/.config/zed/keymap.json").unwrap();
+
+        assert_eq!(
+            lens_title(sync_status_lens(
+                &uri,
+                SyncStatus::RemotelyModified,
+                None,
+                None,
+                0
+            )),
+            (
+                "Remote has newer changes — Pull".to_string(),
+                PULL_COMMAND.to_string()
+            )
+        );
+        assert!(sync_status_lens(&uri, SyncStatus::Error, None, None, 0).is_none());
+    }
+}
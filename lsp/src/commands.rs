@@ -0,0 +1,320 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Result, anyhow, bail};
+use common::sync::{
+    BackupStore, Client as SyncClient, DEFAULT_BACKUP_RETENTION, FileFilter, LocalFileData,
+    LocalFileWriter,
+};
+#[cfg(not(test))]
+use paths as zed_paths;
+#[cfg(test)]
+use test_support::zed_paths;
+use tokio::fs;
+use tower_lsp::lsp_types::{ExecuteCommandParams, Url};
+use tracing::error;
+use zed_extension_api::serde_json::Value;
+
+use crate::watching::{ZedConfigFilePath, ZedConfigPathError};
+
+pub const SYNC_FILE_COMMAND: &str = "settings-sync.syncFile";
+pub const SYNC_ALL_COMMAND: &str = "settings-sync.syncAll";
+pub const PULL_COMMAND: &str = "settings-sync.pull";
+pub const SHOW_REMOTE_REVISION_COMMAND: &str = "settings-sync.showRemoteRevision";
+pub const RELOAD_CONFIG_COMMAND: &str = "settings-sync.reloadConfig";
+
+/// Commands advertised in the `execute_command_provider` capability
+pub const COMMANDS: [&str; 5] = [
+    SYNC_FILE_COMMAND,
+    SYNC_ALL_COMMAND,
+    PULL_COMMAND,
+    SHOW_REMOTE_REVISION_COMMAND,
+    RELOAD_CONFIG_COMMAND,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Syncs the file given by its URI, e.g. the one open in the editor
+    SyncFile(PathBuf),
+    SyncAll,
+    /// Overwrites the local config files with the remote ones
+    Pull,
+    ShowRemoteRevision,
+    /// Re-reads the extension config from the settings file
+    ReloadConfig,
+}
+
+impl Command {
+    pub fn parse(params: &ExecuteCommandParams, file_filter: &FileFilter) -> Result<Self> {
+        match params.command.as_str() {
+            SYNC_FILE_COMMAND => {
+                let uri = params
+                    .arguments
+                    .first()
+                    .and_then(Value::as_str)
+                    .ok_or(anyhow!("{SYNC_FILE_COMMAND} expects a file URI argument"))?;
+                let uri = Url::parse(uri)?;
+                let path = ZedConfigFilePath::from_file_uri(&uri, file_filter).map_err(|err| {
+                    let reason = match err {
+                        ZedConfigPathError::NotZedConfigFile => "not a Zed config file",
+                        ZedConfigPathError::ExcludedFromSync => "excluded from sync",
+                        ZedConfigPathError::WrongFileUriFormat => "wrong file URI format",
+                    };
+                    anyhow!("Can't sync {uri}: {reason}")
+                })?;
+
+                Ok(Command::SyncFile(path.to_watched_path_buf()))
+            }
+            SYNC_ALL_COMMAND => Ok(Command::SyncAll),
+            PULL_COMMAND => Ok(Command::Pull),
+            SHOW_REMOTE_REVISION_COMMAND => Ok(Command::ShowRemoteRevision),
+            RELOAD_CONFIG_COMMAND => Ok(Command::ReloadConfig),
+            command => bail!("Unknown command: {command}"),
+        }
+    }
+}
+
+/// Returns the message to show to the user
+pub async fn sync_file(sync_client: &dyn SyncClient, path: PathBuf) -> Result<String> {
+    let body = fs::read_to_string(&path).await?;
+    let data = LocalFileData::new(path, body)?;
+    let file_name = data.filename.clone();
+
+    sync_client.sync_file(data).await?;
+
+    Ok(format!("Synced {file_name}"))
+}
+
+/// Returns the message to show to the user
+pub async fn sync_all(sync_client: &dyn SyncClient, file_filter: &FileFilter) -> Result<String> {
+    let mut synced = 0;
+    let mut failed = Vec::new();
+
+    let mut entries = fs::read_dir(zed_paths::config_dir()).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let Some(file_name) = path.file_name().and_then(OsStr::to_str) else {
+            continue;
+        };
+        if !path.is_file()
+            || !file_name.to_lowercase().ends_with(".json")
+            || !file_filter.matches(Path::new(file_name))
+        {
+            continue;
+        }
+
+        let file_name = file_name.to_string();
+        match sync_file(sync_client, path).await {
+            Ok(_) => synced += 1,
+            Err(err) => {
+                error!("Could not sync file: {err}");
+                failed.push(file_name);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        failed.sort();
+        bail!(
+            "Synced {synced} file(s), failed to sync: {}",
+            failed.join(", ")
+        );
+    }
+
+    Ok(format!("Synced {synced} file(s)"))
+}
+
+/// Returns the message to show to the user
+pub async fn pull(sync_client: &dyn SyncClient) -> Result<String> {
+    let mut pulled = 0;
+    let mut failed = Vec::new();
+
+    let remote_revision = sync_client.remote_revision().await.ok().flatten();
+    let mut writer = LocalFileWriter::new(
+        &zed_paths::config_dir(),
+        &zed_paths::data_dir(),
+        BackupStore::new(&zed_paths::data_dir(), DEFAULT_BACKUP_RETENTION),
+    );
+
+    for file_result in sync_client.load_files().await? {
+        let (file_name, content) = match file_result {
+            Ok(file) => file,
+            Err(err) => {
+                error!("Could not load file: {err}");
+                writer.log_failure(err.file_name(), err.error(), remote_revision.clone());
+                failed.push(err.file_name().to_string());
+                continue;
+            }
+        };
+
+        match writer.write(&file_name, &content, remote_revision.clone()) {
+            Ok(()) => pulled += 1,
+            Err(err) => {
+                error!("Could not write file {file_name}: {err}");
+                failed.push(file_name);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!(
+            "Pulled {pulled} file(s), failed to pull: {}",
+            failed.join(", ")
+        );
+    }
+
+    Ok(format!("Pulled {pulled} file(s)"))
+}
+
+/// Returns the message to show to the user
+pub async fn show_remote_revision(sync_client: &dyn SyncClient) -> Result<String> {
+    match sync_client.remote_revision().await? {
+        Some(revision) => Ok(format!("Remote revision: {revision}")),
+        None => Ok("Remote storage has no revisions".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use assert_fs::prelude::*;
+    use common::sync::{
+        Error, FileError, MockGithubClient, SyncDirection, SyncEventFilter, SyncEventLog,
+        SyncStateStore, content_hash,
+    };
+    use mockall::predicate;
+    use test_support::zed_config_dir;
+    use zed_extension_api::serde_json::json;
+
+    use super::*;
+
+    fn params(command: &str, arguments: Vec<Value>) -> ExecuteCommandParams {
+        ExecuteCommandParams {
+            command: command.to_string(),
+            arguments,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_commands_are_parsed() -> Result<()> {
+        let keymap = zed_config_dir().child("keymap.json");
+        let uri = Url::from_file_path(keymap.path()).unwrap();
+
+        assert_eq!(
+            Command::parse(
+                &params(SYNC_FILE_COMMAND, vec![json!(uri.as_str())]),
+                &FileFilter::default()
+            )?,
+            Command::SyncFile(keymap.path().to_path_buf())
+        );
+        assert_eq!(
+            Command::parse(&params(PULL_COMMAND, vec![]), &FileFilter::default())?,
+            Command::Pull
+        );
+        assert!(
+            Command::parse(&params(SYNC_FILE_COMMAND, vec![]), &FileFilter::default()).is_err()
+        );
+        assert!(
+            Command::parse(
+                &params(SYNC_FILE_COMMAND, vec![json!("file:///etc/hosts.json")]),
+                &FileFilter::default()
+            )
+            .is_err()
+        );
+        assert!(
+            Command::parse(
+                &params("settings-sync.nope", vec![]),
+                &FileFilter::default()
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_all_reports_failed_files() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+        zed_config_dir().child("tasks.json").write_str("[]")?;
+        zed_config_dir().child("debug.json").write_str("[]")?;
+        zed_config_dir().child("notes.txt").write_str("hello")?;
+
+        let mut mock_sync_client = MockGithubClient::default();
+        mock_sync_client
+            .expect_sync_file()
+            .with(predicate::function(|data: &LocalFileData| {
+                data.filename == "tasks.json"
+            }))
+            .returning(|data| {
+                Err(FileError::from_error(
+                    data.filename,
+                    Error::UnhandledInternal("Sync error".into()),
+                ))
+            });
+        mock_sync_client
+            .expect_sync_file()
+            .with(predicate::function(|data: &LocalFileData| {
+                data.filename == "keymap.json"
+            }))
+            .returning(|_| Ok(()))
+            .once();
+
+        let file_filter = FileFilter::new(&[], &["debug.json".to_string()])?;
+
+        assert_eq!(
+            sync_all(&mock_sync_client, &file_filter)
+                .await
+                .unwrap_err()
+                .to_string(),
+            "Synced 1 file(s), failed to sync: tasks.json"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pulled_files_are_written_locally() -> Result<()> {
+        zed_config_dir().child("keymap.json").write_str("[]")?;
+
+        let mut mock_sync_client = MockGithubClient::default();
+        mock_sync_client
+            .expect_remote_revision()
+            .returning(|| Ok(Some("abc123".to_string())));
+        mock_sync_client.expect_load_files().returning(|| {
+            Ok(Box::new(
+                [Ok((
+                    "keymap.json".to_string(),
+                    "[\n  // @os plan9\n  {}\n  // @end-os\n]\n".to_string(),
+                ))]
+                .into_iter(),
+            ))
+        });
+
+        assert_eq!(pull(&mock_sync_client).await?, "Pulled 1 file(s)");
+
+        let materialized = "[\n  // @os plan9\n  // @end-os\n]\n";
+        zed_config_dir().child("keymap.json").assert(materialized);
+        let data_dir = zed_paths::data_dir();
+        assert_eq!(
+            BackupStore::new(&data_dir, 0).snapshots()?[0].file_names,
+            ["keymap.json"]
+        );
+        assert_eq!(
+            SyncStateStore::new(&data_dir)
+                .load()?
+                .file("keymap.json")
+                .map(|state| state.content_hash.clone()),
+            Some(content_hash(materialized))
+        );
+        let events = SyncEventLog::new(&data_dir).query(&SyncEventFilter::default())?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].direction, SyncDirection::Load);
+        assert_eq!(events[0].remote_revision.as_deref(), Some("abc123"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+use common::{config::CONFIG_KEYS, sync::MASKED_GITHUB_TOKEN};
+use jsonc_parser::{
+    CollectOptions, ParseOptions,
+    ast::{Object, ObjectProp, Value},
+    common::{Range, Ranged},
+    parse_to_ast,
+};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range as LspRange,
+};
+
+const DIAGNOSTIC_SOURCE: &str = "settings-sync";
+
+/// Codes of the published diagnostics, code actions fixing them are matched by these
+pub const INVALID_JSONC_CODE: &str = "invalid-jsonc";
+pub const NOT_CONFIGURED_CODE: &str = "not-configured";
+pub const MISSING_OPTION_CODE: &str = "missing-option";
+pub const MASKED_TOKEN_CODE: &str = "masked-token";
+pub const UNKNOWN_OPTION_CODE: &str = "unknown-option";
+
+/// Problems with the settings sync configuration in the content of the Zed settings file
+pub fn config_diagnostics(text: &str) -> Vec<Diagnostic> {
+    let parse_result =
+        match parse_to_ast(text, &CollectOptions::default(), &ParseOptions::default()) {
+            Ok(parse_result) => parse_result,
+            Err(err) => {
+                return vec![diagnostic(
+                    text,
+                    err.range(),
+                    DiagnosticSeverity::ERROR,
+                    INVALID_JSONC_CODE,
+                    format!("Invalid JSONC, the file can't be synced: {}", err.kind()),
+                )];
+            }
+        };
+
+    let Some(Value::Object(root)) = parse_result.value else {
+        return Vec::new();
+    };
+
+    let mut diagnostics = Vec::new();
+    match find_initialization_options(&root) {
+        Ok(prop) => check_initialization_options(text, prop, &mut diagnostics),
+        Err(closest_range) => diagnostics.push(diagnostic(
+            text,
+            closest_range,
+            DiagnosticSeverity::WARNING,
+            NOT_CONFIGURED_CODE,
+            "Settings sync isn't configured: lsp.settings-sync.initialization_options is missing"
+                .to_string(),
+        )),
+    }
+
+    diagnostics
+}
+
+// the range of the closest existing ancestor if the options are missing
+fn find_initialization_options<'a, 'b>(root: &'b Object<'a>) -> Result<&'b ObjectProp<'a>, Range> {
+    let mut object = root;
+    // the opening brace of the root object
+    let mut closest_range = Range::new(root.start(), root.start() + 1);
+
+    for key in ["lsp", "settings-sync"] {
+        let prop = object.get(key).ok_or(closest_range)?;
+        closest_range = prop.name.range();
+        object = match &prop.value {
+            Value::Object(object) => object,
+            _ => return Err(closest_range),
+        };
+    }
+
+    // the options are hot-reloaded from `settings`, so they may be put there instead
+    object
+        .get("initialization_options")
+        .or_else(|| object.get("settings"))
+        .ok_or(closest_range)
+}
+
+fn check_initialization_options(text: &str, prop: &ObjectProp, diagnostics: &mut Vec<Diagnostic>) {
+    let Value::Object(options) = &prop.value else {
+        diagnostics.push(diagnostic(
+            text,
+            prop.value.range(),
+            DiagnosticSeverity::ERROR,
+            NOT_CONFIGURED_CODE,
+            "initialization_options must be an object".to_string(),
+        ));
+        return;
+    };
+
+    for option in &options.properties {
+        let name = option.name.as_str();
+        if !CONFIG_KEYS.contains(&name) {
+            diagnostics.push(diagnostic(
+                text,
+                option.name.range(),
+                DiagnosticSeverity::WARNING,
+                UNKNOWN_OPTION_CODE,
+                format!("Unknown settings sync option: {name}"),
+            ));
+        }
+    }
+
+    for required in ["gist_id", "github_token"] {
+        match options.get(required).map(|option| &option.value) {
+            None => diagnostics.push(diagnostic(
+                text,
+                prop.name.range(),
+                DiagnosticSeverity::ERROR,
+                MISSING_OPTION_CODE,
+                format!("Missing {required}"),
+            )),
+            Some(Value::StringLit(value)) if value.value.is_empty() => {
+                diagnostics.push(diagnostic(
+                    text,
+                    value.range(),
+                    DiagnosticSeverity::ERROR,
+                    MISSING_OPTION_CODE,
+                    format!("{required} is empty"),
+                ));
+            }
+            Some(Value::StringLit(value))
+                if required == "github_token" && value.value == MASKED_GITHUB_TOKEN =>
+            {
+                diagnostics.push(diagnostic(
+                    text,
+                    value.range(),
+                    DiagnosticSeverity::ERROR,
+                    MASKED_TOKEN_CODE,
+                    "The Github token is masked, it was loaded from the gist; put your token here"
+                        .to_string(),
+                ));
+            }
+            Some(Value::StringLit(_)) => {}
+            Some(value) => diagnostics.push(diagnostic(
+                text,
+                value.range(),
+                DiagnosticSeverity::ERROR,
+                MISSING_OPTION_CODE,
+                format!("{required} must be a string"),
+            )),
+        }
+    }
+}
+
+fn diagnostic(
+    text: &str,
+    range: Range,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range: LspRange::new(position_at(text, range.start), position_at(text, range.end)),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_string())),
+        source: Some(DIAGNOSTIC_SOURCE.to_string()),
+        message,
+        ..Diagnostic::default()
+    }
+}
+
+/// LSP position of the byte offset, LSP counts characters in UTF-16 code units
+pub fn position_at(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+    Position::new(
+        u32::try_from(before.matches('\n').count()).unwrap_or(u32::MAX),
+        u32::try_from(before[line_start..].encode_utf16().count()).unwrap_or(u32::MAX),
+    )
+}
+
+/// Byte offset of the LSP position, clamped to the end of its line
+pub fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return text.len(),
+        }
+    }
+
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+    let character = usize::try_from(position.character).unwrap_or(usize::MAX);
+    let mut code_units = 0;
+    for (index, ch) in line.char_indices() {
+        if code_units >= character {
+            return line_start + index;
+        }
+        code_units += ch.len_utf16();
+    }
+
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str) -> Vec<(String, Position)> {
+        config_diagnostics(text)
+            .into_iter()
+            .map(|diagnostic| {
+                let Some(NumberOrString::String(code)) = diagnostic.code else {
+                    return (String::new(), diagnostic.range.start);
+                };
+                (code, diagnostic.range.start)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_config_has_no_diagnostics() {
+        let text = r#"{
+  // comment
+  "lsp": { "settings-sync": { "initialization_options": {
+    "gist_id": "1234", "github_token": "gho_token", "exclude": ["tasks.json"]
+  } } }
+}"#;
+
+        assert!(config_diagnostics(text).is_empty());
+    }
+
+    #[test]
+    fn test_config_problems_are_reported_at_their_ranges() {
+        let text = r#"{
+  "lsp": { "settings-sync": { "initialization_options": {
+    "github_token": "[masked]",
+    "gits_id": "1234"
+  } } }
+}"#;
+
+        assert_eq!(
+            codes(text),
+            [
+                (UNKNOWN_OPTION_CODE.to_string(), Position::new(3, 4)),
+                (MISSING_OPTION_CODE.to_string(), Position::new(1, 30)),
+                (MASKED_TOKEN_CODE.to_string(), Position::new(2, 20)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_in_server_settings_is_checked() {
+        assert_eq!(
+            codes(r#"{ "lsp": { "settings-sync": { "settings": { "gist_id": "1234" } } } }"#),
+            [(MISSING_OPTION_CODE.to_string(), Position::new(0, 30))]
+        );
+    }
+
+    #[test]
+    fn test_missing_config_is_reported_at_closest_key() {
+        assert_eq!(
+            codes(r#"{ "lsp": { "rust-analyzer": {} } }"#),
+            [(NOT_CONFIGURED_CODE.to_string(), Position::new(0, 2))]
+        );
+        assert_eq!(
+            codes("{}"),
+            [(NOT_CONFIGURED_CODE.to_string(), Position::new(0, 0))]
+        );
+    }
+
+    #[test]
+    fn test_invalid_jsonc_is_reported() {
+        let diagnostics = codes("{\n  \"lsp\": {\n}");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, INVALID_JSONC_CODE);
+    }
+
+    #[test]
+    fn test_positions_count_utf16_code_units() {
+        assert_eq!(position_at("{\n  \"🦀\": 1", 9), Position::new(1, 5));
+        assert_eq!(offset_at("{\n  \"🦀\": 1", Position::new(1, 5)), 9);
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Display};
+
+use common::sync::FileSyncState;
+use jsonc_parser::{
+    CollectOptions, ParseOptions,
+    ast::Value as AstValue,
+    common::{Range, Ranged},
+    parse_to_ast, parse_to_serde_value,
+};
+use zed_extension_api::serde_json::{Value, to_string_pretty};
+
+/// Segment of the JSON path to a settings value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) => write!(f, ".{key}"),
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+        }
+    }
+}
+
+/// JSON path of the object key at the byte offset, `None` if the offset isn't on a key
+pub fn key_path_at(text: &str, offset: usize) -> Option<Vec<PathSegment>> {
+    let parse_result =
+        parse_to_ast(text, &CollectOptions::default(), &ParseOptions::default()).ok()?;
+    let mut path = Vec::new();
+
+    find_key_path(&parse_result.value?, offset, &mut path).then_some(path)
+}
+
+fn find_key_path(value: &AstValue, offset: usize, path: &mut Vec<PathSegment>) -> bool {
+    match value {
+        AstValue::Object(object) => {
+            let Some(prop) = object
+                .properties
+                .iter()
+                .find(|prop| contains(prop.range(), offset))
+            else {
+                return false;
+            };
+            path.push(PathSegment::Key(prop.name.as_str().to_string()));
+
+            contains(prop.name.range(), offset) || find_key_path(&prop.value, offset, path)
+        }
+        AstValue::Array(array) => {
+            let Some((index, element)) = array
+                .elements
+                .iter()
+                .enumerate()
+                .find(|(_, element)| contains(element.range(), offset))
+            else {
+                return false;
+            };
+            path.push(PathSegment::Index(index));
+
+            find_key_path(element, offset, path)
+        }
+        _ => false,
+    }
+}
+
+// the cursor right after the closing quote of a key is still on it
+fn contains(range: Range, offset: usize) -> bool {
+    range.start <= offset && offset <= range.end
+}
+
+/// Markdown comparing the value at the path with the remote copy, `None` for the remote content if it's not in the Gist
+pub fn hover_markdown(
+    path: &[PathSegment],
+    local_text: &str,
+    remote_text: Option<&str>,
+    sync_state: Option<&FileSyncState>,
+) -> String {
+    let path_display: String = path.iter().map(ToString::to_string).collect();
+    let mut lines = vec![format!(
+        "**Settings sync** `{}`",
+        path_display.trim_start_matches('.')
+    )];
+
+    let local_value = parse_value_at(local_text, path);
+    let remote_value = remote_text.map(|remote_text| parse_value_at(remote_text, path));
+    lines.push(match remote_value {
+        None => "The file isn't in the Gist yet".to_string(),
+        Some(None) => "Missing from the synced copy".to_string(),
+        Some(remote_value) if remote_value == local_value => "Same as the synced copy".to_string(),
+        Some(Some(remote_value)) => format!(
+            "Differs from the synced copy, the remote value is:\n```json\n{}\n```",
+            to_string_pretty(&remote_value).unwrap_or_else(|_| remote_value.to_string())
+        ),
+    });
+
+    lines.push(match sync_state {
+        // the local state knows only about the syncs made on this machine
+        Some(state) => format!(
+            "Last synced on this machine at {}",
+            state.synced_at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        None => "Never synced on this machine".to_string(),
+    });
+
+    lines.join("\n\n")
+}
+
+fn parse_value_at(text: &str, path: &[PathSegment]) -> Option<Value> {
+    let mut value = parse_to_serde_value(text, &ParseOptions::default()).ok()??;
+
+    for segment in path {
+        value = match segment {
+            PathSegment::Key(key) => value.get_mut(key)?.take(),
+            PathSegment::Index(index) => value.get_mut(index)?.take(),
+        };
+    }
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn test_key_path_is_resolved_at_offset() {
+        let text = r#"{ "lsp": { "rust-analyzer": { "checkOnSave": true } }, "languages": [{ "tab_size": 2 }] }"#;
+
+        assert_eq!(
+            key_path_at(text, text.find("checkOnSave").unwrap()),
+            Some(vec![
+                PathSegment::Key("lsp".to_string()),
+                PathSegment::Key("rust-analyzer".to_string()),
+                PathSegment::Key("checkOnSave".to_string()),
+            ])
+        );
+        assert_eq!(
+            key_path_at(text, text.find("tab_size").unwrap()),
+            Some(vec![
+                PathSegment::Key("languages".to_string()),
+                PathSegment::Index(0),
+                PathSegment::Key("tab_size".to_string()),
+            ])
+        );
+        // on a value, not on a key
+        assert_eq!(key_path_at(text, text.find("true").unwrap()), None);
+    }
+
+    #[test]
+    fn test_hover_shows_differing_remote_value() {
+        let path = [PathSegment::Key("theme".to_string())];
+        let sync_state = FileSyncState {
+            content_hash: String::new(),
+            synced_at: "2026-01-02T03:04:05Z".parse().unwrap(),
+        };
+
+        let markdown = hover_markdown(
+            &path,
+            r#"{ "theme": "One Dark" }"#,
+            Some(r#"{ "theme": "Ayu Dark" }"#),
+            Some(&sync_state),
+        );
+
+        assert_eq!(
+            markdown,
+            "**Settings sync** `theme`\n\nDiffers from the synced copy, the remote value is:\n```json\n\"Ayu Dark\"\n```\n\nLast synced on this machine at 2026-01-02 03:04 UTC"
+        );
+        assert!(
+            hover_markdown(
+                &path,
+                r#"{ "theme": "One Dark" }"#,
+                Some(r#"{ "theme": "One Dark" }"#),
+                None
+            )
+            .contains("Same as the synced copy")
+        );
+    }
+}
@@ -1,29 +1,168 @@
-use std::env;
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
 
-use tracing::Level;
+#[cfg(not(test))]
+use paths as zed_paths;
+#[cfg(test)]
+use test_support::zed_paths;
+use tracing::{Level, Subscriber, warn};
 use tracing_subscriber::{
     EnvFilter, Layer,
     fmt::{self, time::ChronoLocal},
     layer::SubscriberExt,
+    registry::LookupSpan,
     util::SubscriberInitExt,
 };
 
+const LOG_LEVEL_ENV_VAR: &str = "ZED_SETTINGS_SYNC_LOG_LEVEL";
+const LOG_FILE_ENV_VAR: &str = "ZED_SETTINGS_SYNC_LOG_FILE";
+const LOG_FORMAT_ENV_VAR: &str = "ZED_SETTINGS_SYNC_LOG_FORMAT";
+// every language server process (one per Zed window) writes to its own file, so they don't rotate each other's files
+const LOG_FILE_PREFIX: &str = "settings-sync.";
+const LOG_FILE_EXTENSION: &str = "log";
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const ROTATED_LOG_FILES: usize = 3;
+// the files of the processes that have exited are removed after this time
+const STALE_LOG_FILE_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
 pub fn init_logger() {
-    let log_level = env::var("ZED_SETTINGS_SYNC_LOG_LEVEL")
-        .unwrap_or_else(|_| "info".to_string())
-        .to_lowercase();
-
-    let level = match log_level.as_str() {
-        "trace" => Level::TRACE,
-        "debug" => Level::DEBUG,
-        "info" => Level::INFO,
-        "warn" => Level::WARN,
-        "error" => Level::ERROR,
-        _ => unreachable!(),
+    // reported once the subscriber is set up, so they end up in the log too
+    let mut warnings = Vec::new();
+
+    let log_level = env::var(LOG_LEVEL_ENV_VAR).unwrap_or_else(|_| "info".to_string());
+    let level = parse_level(&log_level).unwrap_or_else(|| {
+        warnings.push(format!(
+            "Unknown {LOG_LEVEL_ENV_VAR} value \"{log_level}\", using \"info\""
+        ));
+        Level::INFO
+    });
+
+    let mut layers = vec![
+        fmt::layer()
+            .with_timer(ChronoLocal::rfc_3339())
+            .with_writer(std::io::stderr)
+            .with_ansi(false)
+            .with_target(false)
+            .with_thread_ids(false)
+            .with_thread_names(false)
+            .with_filter(log_filter(level))
+            .boxed(),
+    ];
+
+    if file_logging_enabled(&mut warnings) {
+        let log_format = env::var(LOG_FORMAT_ENV_VAR).unwrap_or_else(|_| "text".to_string());
+        let format = parse_format(&log_format).unwrap_or_else(|| {
+            warnings.push(format!(
+                "Unknown {LOG_FORMAT_ENV_VAR} value \"{log_format}\", using \"text\""
+            ));
+            LogFormat::Text
+        });
+        let logs_dir = zed_paths::logs_dir();
+        let path = logs_dir.join(log_file_name(process::id()));
+        if let Err(err) = remove_stale_log_files(&logs_dir, SystemTime::now()) {
+            warnings.push(format!("Failed to remove stale log files: {err}"));
+        }
+
+        match RollingFile::open(path.clone(), MAX_LOG_FILE_SIZE, ROTATED_LOG_FILES) {
+            Ok(file) => layers.push(file_layer(file, format, level)),
+            Err(err) => warnings.push(format!(
+                "Failed to open log file {}, logging to stderr only: {err}",
+                path.display()
+            )),
+        }
+    }
+
+    tracing_subscriber::registry().with(layers).init();
+
+    for warning in warnings {
+        warn!("{warning}");
+    }
+}
+
+fn log_file_name(pid: u32) -> String {
+    format!("{LOG_FILE_PREFIX}{pid}.{LOG_FILE_EXTENSION}")
+}
+
+// a file untouched for that long belongs to a process that has exited, or to one with nothing to log
+fn remove_stale_log_files(logs_dir: &Path, now: SystemTime) -> io::Result<()> {
+    let entries = match fs::read_dir(logs_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
     };
 
-    #[allow(clippy::expect_used)]
-    let filter = EnvFilter::from_default_env()
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let is_log_file = file_name.starts_with(LOG_FILE_PREFIX)
+            && file_name.contains(&format!(".{LOG_FILE_EXTENSION}"));
+        let modified = entry.metadata()?.modified()?;
+
+        if is_log_file
+            && now
+                .duration_since(modified)
+                .is_ok_and(|age| age > STALE_LOG_FILE_AGE)
+        {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_level(value: &str) -> Option<Level> {
+    match value.trim().to_lowercase().as_str() {
+        "trace" => Some(Level::TRACE),
+        "debug" => Some(Level::DEBUG),
+        "info" => Some(Level::INFO),
+        "warn" => Some(Level::WARN),
+        "error" => Some(Level::ERROR),
+        _ => None,
+    }
+}
+
+fn parse_format(value: &str) -> Option<LogFormat> {
+    match value.trim().to_lowercase().as_str() {
+        "text" => Some(LogFormat::Text),
+        "json" => Some(LogFormat::Json),
+        _ => None,
+    }
+}
+
+fn file_logging_enabled(warnings: &mut Vec<String>) -> bool {
+    let Ok(value) = env::var(LOG_FILE_ENV_VAR) else {
+        return false;
+    };
+
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" => true,
+        "" | "0" | "false" => false,
+        _ => {
+            warnings.push(format!(
+                "Unknown {LOG_FILE_ENV_VAR} value \"{value}\", logging to stderr only"
+            ));
+            false
+        }
+    }
+}
+
+#[allow(clippy::expect_used)]
+fn log_filter(level: Level) -> EnvFilter {
+    EnvFilter::from_default_env()
         .add_directive(
             format!("common={level}")
                 .parse()
@@ -38,16 +177,148 @@ pub fn init_logger() {
             "tower_lsp=off"
                 .parse()
                 .expect("Failed to parse log filter directive"),
-        ); // silence tower-lsp
+        ) // silence tower-lsp
+}
 
-    let stderr_layer = fmt::layer()
+fn file_layer<S>(
+    file: RollingFile,
+    format: LogFormat,
+    level: Level,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let layer = fmt::layer()
         .with_timer(ChronoLocal::rfc_3339())
-        .with_writer(std::io::stderr)
-        .with_ansi(false)
-        .with_target(false)
-        .with_thread_ids(false)
-        .with_thread_names(false)
-        .with_filter(filter);
-
-    tracing_subscriber::registry().with(stderr_layer).init();
+        .with_writer(Mutex::new(file))
+        .with_ansi(false);
+
+    match format {
+        LogFormat::Text => layer.with_filter(log_filter(level)).boxed(),
+        LogFormat::Json => layer.json().with_filter(log_filter(level)).boxed(),
+    }
+}
+
+/// Log file moved aside once it grows over the size limit, keeping the previous ones as `<name>.1`, `<name>.2`, ...
+#[derive(Debug)]
+struct RollingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_rotated: usize,
+    file: File,
+    size: u64,
+}
+
+impl RollingFile {
+    fn open(path: PathBuf, max_size: u64, max_rotated: usize) -> io::Result<Self> {
+        if let Some(log_dir) = path.parent() {
+            fs::create_dir_all(log_dir)?;
+        }
+        let file = open_for_append(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size,
+            max_rotated,
+            file,
+            size,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // the oldest file gets overwritten
+        for index in (1..self.max_rotated).rev() {
+            let rotated_path = self.rotated_path(index);
+            if rotated_path.try_exists()? {
+                fs::rename(&rotated_path, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = open_for_append(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = OsString::from(&self.path);
+        path.push(format!(".{index}"));
+        path.into()
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // every event is written at once, so it never gets split between files
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_for_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use assert_fs::{TempDir, prelude::*};
+
+    use super::*;
+
+    #[test]
+    fn test_log_level_and_format_are_parsed() {
+        assert_eq!(parse_level("DEBUG"), Some(Level::DEBUG));
+        assert_eq!(parse_level(" warn "), Some(Level::WARN));
+        assert_eq!(parse_level("verbose"), None);
+        assert_eq!(parse_format("JSON"), Some(LogFormat::Json));
+        assert_eq!(parse_format("yaml"), None);
+    }
+
+    #[test]
+    fn test_log_file_is_rotated_over_size_limit() {
+        let logs_dir = TempDir::new().unwrap();
+        let file_name = log_file_name(42);
+        let mut file = RollingFile::open(logs_dir.path().join(&file_name), 10, 2).unwrap();
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        logs_dir.child(&file_name).assert("fourth\n");
+        logs_dir.child(format!("{file_name}.1")).assert("third\n");
+        logs_dir.child(format!("{file_name}.2")).assert("second\n");
+        assert!(!logs_dir.child(format!("{file_name}.3")).path().exists());
+    }
+
+    #[test]
+    fn test_stale_log_files_are_removed() {
+        let logs_dir = TempDir::new().unwrap();
+        for file_name in ["settings-sync.1.log", "settings-sync.1.log.1", "other.log"] {
+            logs_dir.child(file_name).touch().unwrap();
+        }
+
+        remove_stale_log_files(logs_dir.path(), SystemTime::now()).unwrap();
+        assert!(logs_dir.child("settings-sync.1.log").path().exists());
+
+        let later = SystemTime::now() + STALE_LOG_FILE_AGE + Duration::from_secs(1);
+        remove_stale_log_files(logs_dir.path(), later).unwrap();
+        assert!(!logs_dir.child("settings-sync.1.log").path().exists());
+        assert!(!logs_dir.child("settings-sync.1.log.1").path().exists());
+        assert!(logs_dir.child("other.log").path().exists());
+    }
 }
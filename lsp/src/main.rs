@@ -7,9 +7,16 @@ use crate::backend::Backend;
 
 mod app_state;
 mod backend;
+mod code_actions;
+mod code_lens;
+mod commands;
+mod diagnostics;
+mod hover;
 mod logger;
 #[cfg(test)]
 mod mocks;
+mod remote_snapshot;
+mod sync_reporter;
 mod watching;
 
 #[cfg(test)]
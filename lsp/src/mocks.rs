@@ -1,11 +1,19 @@
 use std::fmt;
 
 use mockall::mock;
-use tower_lsp::lsp_types::MessageType;
+use tower_lsp::lsp_types::{
+    ConfigurationItem, Diagnostic, MessageType, NumberOrString, Url, WorkDoneProgress,
+};
+use zed_extension_api::serde_json::Value;
 
 mock! {
     pub LspClient {
         pub fn show_message(&self, msg_type: MessageType, message: String) -> impl Future<Output = ()> + Send + Sync;
+        pub fn create_work_done_progress(&self, token: NumberOrString) -> impl Future<Output = tower_lsp::jsonrpc::Result<()>> + Send + Sync;
+        pub fn send_work_done_progress(&self, token: NumberOrString, progress: WorkDoneProgress) -> impl Future<Output = ()> + Send + Sync;
+        pub fn configuration(&self, items: Vec<ConfigurationItem>) -> impl Future<Output = tower_lsp::jsonrpc::Result<Vec<Value>>> + Send + Sync;
+        pub fn publish_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>, version: Option<i32>) -> impl Future<Output = ()> + Send + Sync;
+        pub fn code_lens_refresh(&self) -> impl Future<Output = tower_lsp::jsonrpc::Result<()>> + Send + Sync;
     }
 
     impl Clone for LspClient {
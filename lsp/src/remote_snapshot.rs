@@ -0,0 +1,114 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use common::sync::{CURRENT_OS, Client as SyncClient, materialize_os_sections};
+
+// remote changes made from other machines show up after this time at most
+const SNAPSHOT_TTL: Duration = Duration::from_secs(60);
+
+/// Gist content the way `load` would write it locally
+#[derive(Debug, Clone, Default)]
+pub struct RemoteFiles {
+    pub revision: Option<String>,
+    pub files: HashMap<String, String>,
+}
+
+/// Remote files shared between hovers and code lenses, loaded from the Gist again once stale
+#[derive(Debug, Default)]
+pub struct RemoteSnapshot {
+    loaded: Mutex<Option<(Instant, RemoteFiles)>>,
+}
+
+impl RemoteSnapshot {
+    pub async fn load(&self, sync_client: &dyn SyncClient) -> Result<RemoteFiles> {
+        if let Some(remote_files) = self.fresh() {
+            return Ok(remote_files);
+        }
+
+        let revision = sync_client.remote_revision().await?;
+        // the files that failed to be transformed can't be compared anyway
+        let files = sync_client
+            .load_files()
+            .await?
+            .filter_map(Result::ok)
+            .filter_map(|(file_name, content)| {
+                Some((
+                    file_name,
+                    materialize_os_sections(&content, CURRENT_OS).ok()?,
+                ))
+            })
+            .collect();
+        let remote_files = RemoteFiles { revision, files };
+
+        #[allow(clippy::expect_used)]
+        let mut loaded = self
+            .loaded
+            .lock()
+            .expect("Remote snapshot mutex is poisoned");
+        *loaded = Some((Instant::now(), remote_files.clone()));
+
+        Ok(remote_files)
+    }
+
+    /// Makes the next request load the remote files, e.g. after they were updated by a sync
+    pub fn invalidate(&self) {
+        #[allow(clippy::expect_used)]
+        let mut loaded = self
+            .loaded
+            .lock()
+            .expect("Remote snapshot mutex is poisoned");
+        *loaded = None;
+    }
+
+    fn fresh(&self) -> Option<RemoteFiles> {
+        #[allow(clippy::expect_used)]
+        let loaded = self
+            .loaded
+            .lock()
+            .expect("Remote snapshot mutex is poisoned");
+
+        loaded
+            .as_ref()
+            .filter(|(loaded_at, _)| loaded_at.elapsed() < SNAPSHOT_TTL)
+            .map(|(_, remote_files)| remote_files.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use common::sync::MockGithubClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remote_files_are_loaded_once_until_invalidated() {
+        let mut mock_sync_client = MockGithubClient::default();
+        mock_sync_client
+            .expect_remote_revision()
+            .times(2)
+            .returning(|| Ok(Some("abc123".to_string())));
+        mock_sync_client.expect_load_files().times(2).returning(|| {
+            Ok(Box::new(
+                [Ok(("settings.json".to_string(), "{}".to_string()))].into_iter(),
+            ))
+        });
+        let snapshot = RemoteSnapshot::default();
+
+        let remote_files = snapshot.load(&mock_sync_client).await.unwrap();
+        assert_eq!(remote_files.revision.as_deref(), Some("abc123"));
+        assert_eq!(
+            remote_files.files.get("settings.json").map(String::as_str),
+            Some("{}")
+        );
+
+        snapshot.load(&mock_sync_client).await.unwrap();
+        snapshot.invalidate();
+        snapshot.load(&mock_sync_client).await.unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use common::{config::SyncNotifications, sync::FileError};
+#[cfg(not(test))]
+use tower_lsp::Client as LspClient;
+use tower_lsp::lsp_types::{
+    MessageType, NumberOrString, WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressEnd,
+};
+use tracing::debug;
+
+#[cfg(test)]
+use crate::mocks::MockLspClient as LspClient;
+
+/// Work done progress requests that the tower-lsp client has no dedicated methods for
+#[cfg(not(test))]
+#[tower_lsp::async_trait]
+pub trait WorkDoneProgressClient {
+    async fn create_work_done_progress(
+        &self,
+        token: NumberOrString,
+    ) -> tower_lsp::jsonrpc::Result<()>;
+
+    async fn send_work_done_progress(&self, token: NumberOrString, progress: WorkDoneProgress);
+}
+
+#[cfg(not(test))]
+#[tower_lsp::async_trait]
+impl WorkDoneProgressClient for LspClient {
+    async fn create_work_done_progress(
+        &self,
+        token: NumberOrString,
+    ) -> tower_lsp::jsonrpc::Result<()> {
+        use tower_lsp::lsp_types::{WorkDoneProgressCreateParams, request::WorkDoneProgressCreate};
+
+        self.send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams { token })
+            .await
+    }
+
+    async fn send_work_done_progress(&self, token: NumberOrString, progress: WorkDoneProgress) {
+        use tower_lsp::lsp_types::{ProgressParams, ProgressParamsValue, notification::Progress};
+
+        self.send_notification::<Progress>(ProgressParams {
+            token,
+            value: ProgressParamsValue::WorkDone(progress),
+        })
+        .await;
+    }
+}
+
+/// Reports file syncs to the user as work done progress and, depending on the config, popups
+#[derive(Debug)]
+pub struct SyncReporter {
+    lsp_client: Arc<LspClient>,
+    notifications: RwLock<SyncNotifications>,
+    synced_once: AtomicBool,
+    next_token: AtomicU64,
+}
+
+impl SyncReporter {
+    pub fn new(lsp_client: Arc<LspClient>, notifications: SyncNotifications) -> Self {
+        Self {
+            lsp_client,
+            notifications: RwLock::new(notifications),
+            synced_once: AtomicBool::new(false),
+            next_token: AtomicU64::new(0),
+        }
+    }
+
+    pub fn set_notifications(&self, notifications: SyncNotifications) {
+        #[allow(clippy::expect_used)]
+        let mut current = self
+            .notifications
+            .write()
+            .expect("Notifications lock is poisoned");
+        *current = notifications;
+    }
+
+    /// Returns the progress token to end the progress with, `None` if the client doesn't support progress
+    pub async fn begin(&self, file_name: &str) -> Option<NumberOrString> {
+        let token = NumberOrString::String(format!(
+            "settings-sync/{}",
+            self.next_token.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        if let Err(err) = self
+            .lsp_client
+            .create_work_done_progress(token.clone())
+            .await
+        {
+            debug!("Work done progress is not available: {err}");
+            return None;
+        }
+
+        self.lsp_client
+            .send_work_done_progress(
+                token.clone(),
+                WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                    title: format!("Syncing {file_name}"),
+                    ..WorkDoneProgressBegin::default()
+                }),
+            )
+            .await;
+
+        Some(token)
+    }
+
+    pub async fn end(&self, token: Option<NumberOrString>, result: &Result<(), FileError>) {
+        if let Some(token) = token {
+            let message = match result {
+                Ok(()) => "Synced".to_string(),
+                Err(err) => format!("Failed: {err}"),
+            };
+            self.lsp_client
+                .send_work_done_progress(
+                    token,
+                    WorkDoneProgress::End(WorkDoneProgressEnd {
+                        message: Some(message),
+                    }),
+                )
+                .await;
+        }
+
+        match result {
+            Ok(()) => {
+                let first_sync = !self.synced_once.swap(true, Ordering::Relaxed);
+                let show_popup = match self.notifications() {
+                    SyncNotifications::Always => true,
+                    SyncNotifications::OnFirstSync => first_sync,
+                    SyncNotifications::Never | SyncNotifications::ErrorsOnly => false,
+                };
+                if show_popup {
+                    self.lsp_client
+                        .show_message(MessageType::INFO, "Successfully synced".to_owned())
+                        .await;
+                }
+            }
+            Err(err) => self.show_error(err.to_string()).await,
+        }
+    }
+
+    /// Makes the client request the code lenses again, the sync status they show may have changed
+    pub async fn refresh_code_lenses(&self) {
+        if let Err(err) = self.lsp_client.code_lens_refresh().await {
+            debug!("Code lens refresh is not available: {err}");
+        }
+    }
+
+    pub async fn show_error(&self, message: String) {
+        if self.notifications() != SyncNotifications::Never {
+            self.lsp_client
+                .show_message(MessageType::ERROR, message)
+                .await;
+        }
+    }
+
+    fn notifications(&self) -> SyncNotifications {
+        #[allow(clippy::expect_used)]
+        *self
+            .notifications
+            .read()
+            .expect("Notifications lock is poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::sync::Error;
+    use mockall::predicate;
+
+    use super::*;
+
+    fn progress_lsp_client() -> LspClient {
+        let mut mock_lsp_client = LspClient::default();
+        mock_lsp_client
+            .expect_create_work_done_progress()
+            .returning(|_token| Box::pin(async { Ok(()) }));
+        mock_lsp_client
+            .expect_send_work_done_progress()
+            .returning(|_token, _progress| Box::pin(async {}));
+        mock_lsp_client
+    }
+
+    #[tokio::test]
+    async fn test_progress_begins_and_ends_with_result() {
+        let mut seq = mockall::Sequence::new();
+        let mut mock_lsp_client = LspClient::default();
+        mock_lsp_client
+            .expect_create_work_done_progress()
+            .in_sequence(&mut seq)
+            .returning(|_token| Box::pin(async { Ok(()) }));
+        mock_lsp_client
+            .expect_send_work_done_progress()
+            .in_sequence(&mut seq)
+            .withf(|_token, progress| {
+                matches!(progress, WorkDoneProgress::Begin(begin) if begin.title == "Syncing keymap.json")
+            })
+            .returning(|_token, _progress| Box::pin(async {}));
+        mock_lsp_client
+            .expect_send_work_done_progress()
+            .in_sequence(&mut seq)
+            .withf(|_token, progress| {
+                matches!(progress, WorkDoneProgress::End(end) if end.message.as_deref() == Some("Synced"))
+            })
+            .returning(|_token, _progress| Box::pin(async {}));
+        mock_lsp_client.expect_show_message().never();
+
+        let reporter = SyncReporter::new(Arc::new(mock_lsp_client), SyncNotifications::ErrorsOnly);
+        let token = reporter.begin("keymap.json").await;
+        reporter.end(token, &Ok(())).await;
+    }
+
+    #[tokio::test]
+    async fn test_success_popup_is_shown_on_first_sync_only() {
+        let mut mock_lsp_client = progress_lsp_client();
+        mock_lsp_client
+            .expect_show_message()
+            .with(
+                predicate::eq(MessageType::INFO),
+                predicate::eq("Successfully synced".to_owned()),
+            )
+            .returning(|_msg_type, _msg| Box::pin(async {}))
+            .once();
+
+        let reporter = SyncReporter::new(Arc::new(mock_lsp_client), SyncNotifications::OnFirstSync);
+        for _ in 0..2 {
+            let token = reporter.begin("keymap.json").await;
+            reporter.end(token, &Ok(())).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_shown_with_notifications_disabled() {
+        let mut mock_lsp_client = LspClient::default();
+        mock_lsp_client
+            .expect_create_work_done_progress()
+            .returning(|_token| {
+                Box::pin(async { Err(tower_lsp::jsonrpc::Error::method_not_found()) })
+            });
+        mock_lsp_client.expect_send_work_done_progress().never();
+        mock_lsp_client.expect_show_message().never();
+
+        let reporter = SyncReporter::new(Arc::new(mock_lsp_client), SyncNotifications::Never);
+        let token = reporter.begin("keymap.json").await;
+        reporter
+            .end(
+                token,
+                &Err(FileError::from_error(
+                    "keymap.json",
+                    Error::UnhandledInternal("Sync error".into()),
+                )),
+            )
+            .await;
+    }
+}
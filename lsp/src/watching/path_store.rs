@@ -1,33 +1,64 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Mutex, RwLock};
 use std::{path::PathBuf, pin::Pin, sync::Arc};
 
 use anyhow::Result;
 use anyhow::{Context, anyhow};
+use common::config::SyncTrigger;
 use common::sync::{Client as SyncClient, LocalFileData};
+use debug_ignore::DebugIgnore;
 use mockall_double::double;
 use notify::{Event, EventKind, event::ModifyKind};
 use tokio::fs;
-#[cfg(not(test))]
-use tower_lsp::Client as LspClient;
-use tower_lsp::lsp_types::MessageType;
+use tower_lsp::lsp_types::Url;
 use tracing::{debug, error};
 
-#[cfg(test)]
-use crate::mocks::MockLspClient as LspClient;
+use crate::remote_snapshot::RemoteSnapshot;
+use crate::sync_reporter::SyncReporter;
 #[double]
 use crate::watching::WatchedSet;
 
+/// Content of the documents open in the editor, kept up to date by the full text sync
+pub type OpenDocuments = Arc<Mutex<HashMap<Url, String>>>;
+
 #[derive(Debug)]
 pub struct PathStore {
     watched_set: WatchedSet,
+    // shared with the event handler, so the client can be swapped while the files are being watched
+    sync_client: DebugIgnore<Arc<RwLock<Arc<dyn SyncClient>>>>,
+    // in save mode the open documents are synced on save, the watcher syncs only the changes made outside the editor
+    sync_trigger: Arc<RwLock<SyncTrigger>>,
 }
 
 #[cfg_attr(test, mockall::automock)]
 impl PathStore {
-    pub fn new(sync_client: Arc<dyn SyncClient>, lsp_client: Arc<LspClient>) -> Result<Self> {
+    pub fn new(
+        sync_client: Arc<dyn SyncClient>,
+        reporter: Arc<SyncReporter>,
+        sync_trigger: SyncTrigger,
+        open_documents: OpenDocuments,
+        remote_snapshot: Arc<RemoteSnapshot>,
+    ) -> Result<Self> {
+        let sync_client = Arc::new(RwLock::new(sync_client));
+        let handler_sync_client = Arc::clone(&sync_client);
+        let sync_trigger = Arc::new(RwLock::new(sync_trigger));
+        let handler_sync_trigger = Arc::clone(&sync_trigger);
+
         let event_handler = Box::new(move |event| {
-            let sync_client_clone = Arc::clone(&sync_client);
-            let lsp_client_clone = Arc::clone(&lsp_client);
+            #[allow(clippy::expect_used)]
+            let sync_client_clone = Arc::clone(
+                &handler_sync_client
+                    .read()
+                    .expect("Sync client lock is poisoned"),
+            );
+            #[allow(clippy::expect_used)]
+            let sync_trigger = *handler_sync_trigger
+                .read()
+                .expect("Sync trigger lock is poisoned");
+            let reporter_clone = Arc::clone(&reporter);
+            let open_documents_clone = Arc::clone(&open_documents);
+            let remote_snapshot_clone = Arc::clone(&remote_snapshot);
 
             Box::pin(async move {
                 match process_event(&event).await {
@@ -35,29 +66,28 @@ impl PathStore {
                         let Some(data) = data else {
                             return;
                         };
+                        if sync_trigger == SyncTrigger::Save
+                            && is_open_and_saved(&open_documents_clone, &data)
+                        {
+                            debug!("File is synced on save, skipping: {}", data.path.display());
+                            return;
+                        }
 
-                        match sync_client_clone.sync_file(data).await {
-                            Ok(()) => {
-                                lsp_client_clone
-                                    .show_message(
-                                        MessageType::INFO,
-                                        "Successfully synced".to_owned(),
-                                    )
-                                    .await;
-                            }
-                            Err(err) => {
-                                error!("Could not sync file: {err}");
-                                lsp_client_clone
-                                    .show_message(MessageType::ERROR, err.to_string())
-                                    .await;
-                            }
+                        let progress_token = reporter_clone.begin(&data.filename).await;
+                        let result = sync_client_clone.sync_file(data).await;
+                        match &result {
+                            Ok(()) => remote_snapshot_clone.invalidate(),
+                            Err(err) => error!("Could not sync file: {err}"),
+                        }
+                        reporter_clone.end(progress_token, &result).await;
+                        if result.is_ok() {
+                            reporter_clone.refresh_code_lenses().await;
                         }
                     }
                     Err(err) => {
                         error!("Could not process file event: {err}");
-                        lsp_client_clone
-                            .show_message(
-                                MessageType::ERROR,
+                        reporter_clone
+                            .show_error(
                                 "File watcher internal error, check LSP server logs".to_owned(),
                             )
                             .await;
@@ -68,9 +98,30 @@ impl PathStore {
 
         Ok(Self {
             watched_set: WatchedSet::new(event_handler)?,
+            sync_client: DebugIgnore(sync_client),
+            sync_trigger,
         })
     }
 
+    /// Makes the files that are already watched sync through the given client from now on
+    pub fn set_sync_client(&mut self, sync_client: Arc<dyn SyncClient>) {
+        #[allow(clippy::expect_used)]
+        let mut current = self
+            .sync_client
+            .write()
+            .expect("Sync client lock is poisoned");
+        *current = sync_client;
+    }
+
+    pub fn set_sync_trigger(&mut self, sync_trigger: SyncTrigger) {
+        #[allow(clippy::expect_used)]
+        let mut current = self
+            .sync_trigger
+            .write()
+            .expect("Sync trigger lock is poisoned");
+        *current = sync_trigger;
+    }
+
     pub fn start_watcher(&mut self) {
         self.watched_set.start_watcher();
     }
@@ -84,6 +135,24 @@ impl PathStore {
     pub fn unwatch(&mut self, file_path: &Path) -> anyhow::Result<()> {
         self.watched_set.unwatch(file_path)
     }
+
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        self.watched_set.paths()
+    }
+}
+
+// a saved document has the same content on disk, whichever of the save notification and the watcher event comes first
+fn is_open_and_saved(open_documents: &Mutex<HashMap<Url, String>>, data: &LocalFileData) -> bool {
+    let Ok(uri) = Url::from_file_path(&data.path) else {
+        return false;
+    };
+
+    #[allow(clippy::expect_used)]
+    open_documents
+        .lock()
+        .expect("Documents mutex is poisoned")
+        .get(&uri)
+        .is_some_and(|text| *text == data.body)
 }
 
 async fn process_event(event: &Event) -> Result<Option<LocalFileData>> {
@@ -110,11 +179,15 @@ mod tests {
     #![allow(clippy::unwrap_used)]
 
     use assert_fs::{NamedTempFile, TempDir, prelude::*};
-    use common::sync::{Error, FileError, MockGithubClient};
+    use common::{
+        config::SyncNotifications,
+        sync::{Error, FileError, MockGithubClient},
+    };
     use mockall::predicate;
     use notify::event::{AccessKind, AccessMode, CreateKind, DataChange, RemoveKind};
     use paste::paste;
     use tokio::runtime::Runtime;
+    use tower_lsp::lsp_types::MessageType;
 
     use super::*;
     use crate::{
@@ -122,6 +195,30 @@ mod tests {
         watching::{EventHandler, MockWatchedSet},
     };
 
+    fn reporter(mock_lsp_client: MockLspClient) -> Arc<SyncReporter> {
+        Arc::new(SyncReporter::new(
+            Arc::new(mock_lsp_client),
+            SyncNotifications::Always,
+        ))
+    }
+
+    fn expect_progress(mock_lsp_client: &mut MockLspClient) {
+        mock_lsp_client
+            .expect_create_work_done_progress()
+            .returning(|_token| Box::pin(async { Ok(()) }));
+        mock_lsp_client
+            .expect_send_work_done_progress()
+            .returning(|_token, _progress| Box::pin(async {}));
+    }
+
+    // the code lenses show the sync status, so they are refreshed once per successful sync
+    fn expect_code_lens_refresh(mock_lsp_client: &mut MockLspClient) {
+        mock_lsp_client
+            .expect_code_lens_refresh()
+            .once()
+            .returning(|| Box::pin(async { Ok(()) }));
+    }
+
     #[test]
     fn test_creation_success() {
         let ctx = MockWatchedSet::new_context();
@@ -130,7 +227,10 @@ mod tests {
         assert!(
             PathStore::new(
                 Arc::new(MockGithubClient::default()),
-                Arc::new(MockLspClient::default())
+                reporter(MockLspClient::default()),
+                SyncTrigger::Watcher,
+                OpenDocuments::default(),
+                Arc::default(),
             )
             .is_ok()
         );
@@ -145,7 +245,10 @@ mod tests {
         assert!(
             PathStore::new(
                 Arc::new(MockGithubClient::default()),
-                Arc::new(MockLspClient::default())
+                reporter(MockLspClient::default()),
+                SyncTrigger::Watcher,
+                OpenDocuments::default(),
+                Arc::default(),
             )
             .is_err()
         );
@@ -205,7 +308,10 @@ mod tests {
 
         let mut store = PathStore::new(
             Arc::new(MockGithubClient::default()),
-            Arc::new(MockLspClient::default()),
+            reporter(MockLspClient::default()),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
         )?;
         store.start_watcher();
         store.watch(path_clone)?;
@@ -224,7 +330,10 @@ mod tests {
 
         let mut store = PathStore::new(
             Arc::new(MockGithubClient::default()),
-            Arc::new(MockLspClient::default()),
+            reporter(MockLspClient::default()),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
         )?;
         store.start_watcher();
 
@@ -247,7 +356,10 @@ mod tests {
 
         let mut store = PathStore::new(
             Arc::new(MockGithubClient::default()),
-            Arc::new(MockLspClient::default()),
+            reporter(MockLspClient::default()),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
         )?;
         store.start_watcher();
         store.unwatch(&path_clone)?;
@@ -266,7 +378,10 @@ mod tests {
 
         let mut store = PathStore::new(
             Arc::new(MockGithubClient::default()),
-            Arc::new(MockLspClient::default()),
+            reporter(MockLspClient::default()),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
         )?;
         store.start_watcher();
 
@@ -307,7 +422,10 @@ mod tests {
 
         PathStore::new(
             Arc::new(mock_sync_client),
-            Arc::new(MockLspClient::default()),
+            reporter(MockLspClient::default()),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
         )?;
 
         Ok(())
@@ -340,7 +458,13 @@ mod tests {
             )
             .return_once(|_msg_type, _msg| Box::pin(async {}));
 
-        PathStore::new(Arc::new(mock_sync_client), Arc::new(mock_lsp_client))?;
+        PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
+        )?;
 
         Ok(())
     }
@@ -373,7 +497,13 @@ mod tests {
             )
             .return_once(|_msg_type, _msg| Box::pin(async {}));
 
-        PathStore::new(Arc::new(mock_sync_client), Arc::new(mock_lsp_client))?;
+        PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
+        )?;
 
         Ok(())
     }
@@ -407,6 +537,8 @@ mod tests {
             .return_once(|_| Ok(()));
 
         let mut mock_lsp_client = MockLspClient::default();
+        expect_progress(&mut mock_lsp_client);
+        expect_code_lens_refresh(&mut mock_lsp_client);
         mock_lsp_client
             .expect_show_message()
             .with(
@@ -415,7 +547,13 @@ mod tests {
             )
             .return_once(|_msg_type, _msg| Box::pin(async {}));
 
-        PathStore::new(Arc::new(mock_sync_client), Arc::new(mock_lsp_client))?;
+        PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
+        )?;
 
         Ok(())
     }
@@ -454,6 +592,7 @@ mod tests {
             });
 
         let mut mock_lsp_client = MockLspClient::default();
+        expect_progress(&mut mock_lsp_client);
         mock_lsp_client
             .expect_show_message()
             .with(
@@ -462,7 +601,180 @@ mod tests {
             )
             .return_once(|_msg_type, _msg| Box::pin(async {}));
 
-        PathStore::new(Arc::new(mock_sync_client), Arc::new(mock_lsp_client))?;
+        PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modify_event_handling_with_swapped_sync_client() -> Result<()> {
+        let temp_file = NamedTempFile::new("settings.json")?;
+        temp_file.write_str(r#"{ "hello": "kitty" }"#)?;
+
+        // keeping the handler to call it after the client is swapped
+        let handler_slot: Arc<std::sync::Mutex<Option<EventHandler>>> = Arc::default();
+        let ctx = MockWatchedSet::new_context();
+        ctx.expect().returning({
+            let handler_slot = Arc::clone(&handler_slot);
+            move |event_handler: EventHandler| {
+                *handler_slot.lock().unwrap() = Some(event_handler);
+                Ok(MockWatchedSet::default())
+            }
+        });
+
+        let file_data = LocalFileData::new(
+            temp_file.path().to_path_buf(),
+            r#"{ "hello": "kitty" }"#.into(),
+        )?;
+
+        let mut old_sync_client = MockGithubClient::default();
+        old_sync_client.expect_sync_file().never();
+        let mut new_sync_client = MockGithubClient::default();
+        new_sync_client
+            .expect_sync_file()
+            .with(predicate::eq(file_data))
+            .return_once(|_| Ok(()));
+
+        let mut mock_lsp_client = MockLspClient::default();
+        expect_progress(&mut mock_lsp_client);
+        expect_code_lens_refresh(&mut mock_lsp_client);
+        mock_lsp_client
+            .expect_show_message()
+            .with(
+                predicate::eq(MessageType::INFO),
+                predicate::eq("Successfully synced".to_owned()),
+            )
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+
+        let mut store = PathStore::new(
+            Arc::new(old_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Watcher,
+            OpenDocuments::default(),
+            Arc::default(),
+        )?;
+        store.set_sync_client(Arc::new(new_sync_client));
+
+        let event_handler = handler_slot.lock().unwrap().take().unwrap();
+        let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(temp_file.path().to_path_buf());
+        Runtime::new()?.block_on(event_handler(event));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modify_event_handling_skips_saved_open_document_in_save_mode() -> Result<()> {
+        let temp_file = NamedTempFile::new("settings.json")?;
+        temp_file.write_str(r#"{ "hello": "kitty" }"#)?;
+
+        let handler_slot: Arc<std::sync::Mutex<Option<EventHandler>>> = Arc::default();
+        let ctx = MockWatchedSet::new_context();
+        ctx.expect().returning({
+            let handler_slot = Arc::clone(&handler_slot);
+            move |event_handler: EventHandler| {
+                *handler_slot.lock().unwrap() = Some(event_handler);
+                Ok(MockWatchedSet::default())
+            }
+        });
+
+        // only the content changed outside the editor is synced
+        let external_data = LocalFileData::new(
+            temp_file.path().to_path_buf(),
+            r#"{ "hello": "world" }"#.into(),
+        )?;
+        let mut mock_sync_client = MockGithubClient::default();
+        mock_sync_client
+            .expect_sync_file()
+            .with(predicate::eq(external_data))
+            .once()
+            .return_once(|_| Ok(()));
+
+        let mut mock_lsp_client = MockLspClient::default();
+        expect_progress(&mut mock_lsp_client);
+        expect_code_lens_refresh(&mut mock_lsp_client);
+        mock_lsp_client
+            .expect_show_message()
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+
+        // the buffer being saved, the watcher event comes before the save notification
+        let open_documents = OpenDocuments::default();
+        open_documents.lock().unwrap().insert(
+            Url::from_file_path(temp_file.path()).unwrap(),
+            r#"{ "hello": "kitty" }"#.to_string(),
+        );
+        let _store = PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Save,
+            open_documents,
+            Arc::default(),
+        )?;
+
+        let event_handler = handler_slot.lock().unwrap().take().unwrap();
+        let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(temp_file.path().to_path_buf());
+        let rt = Runtime::new()?;
+        rt.block_on(event_handler(event.clone()));
+
+        temp_file.write_str(r#"{ "hello": "world" }"#)?;
+        rt.block_on(event_handler(event));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modify_event_handling_syncs_open_document_in_watcher_mode() -> Result<()> {
+        let temp_file = NamedTempFile::new("settings.json")?;
+        temp_file.write_str(r#"{ "hello": "kitty" }"#)?;
+
+        let handler_slot: Arc<std::sync::Mutex<Option<EventHandler>>> = Arc::default();
+        let ctx = MockWatchedSet::new_context();
+        ctx.expect().returning({
+            let handler_slot = Arc::clone(&handler_slot);
+            move |event_handler: EventHandler| {
+                *handler_slot.lock().unwrap() = Some(event_handler);
+                Ok(MockWatchedSet::default())
+            }
+        });
+
+        let mut mock_sync_client = MockGithubClient::default();
+        mock_sync_client
+            .expect_sync_file()
+            .once()
+            .return_once(|_| Ok(()));
+
+        let mut mock_lsp_client = MockLspClient::default();
+        expect_progress(&mut mock_lsp_client);
+        expect_code_lens_refresh(&mut mock_lsp_client);
+        mock_lsp_client
+            .expect_show_message()
+            .return_once(|_msg_type, _msg| Box::pin(async {}));
+
+        let open_documents = OpenDocuments::default();
+        open_documents.lock().unwrap().insert(
+            Url::from_file_path(temp_file.path()).unwrap(),
+            r#"{ "hello": "kitty" }"#.to_string(),
+        );
+        let mut store = PathStore::new(
+            Arc::new(mock_sync_client),
+            reporter(mock_lsp_client),
+            SyncTrigger::Save,
+            open_documents,
+            Arc::default(),
+        )?;
+        store.set_sync_trigger(SyncTrigger::Watcher);
+
+        let event_handler = handler_slot.lock().unwrap().take().unwrap();
+        let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(temp_file.path().to_path_buf());
+        Runtime::new()?.block_on(event_handler(event));
 
         Ok(())
     }
@@ -51,6 +51,13 @@ impl WatchedSet {
         Ok(())
     }
 
+    pub fn paths(&self) -> Vec<PathBuf> {
+        #[allow(clippy::expect_used)]
+        let _lock = self.mx.lock().expect("Watched set mutex is poisoned");
+
+        self.paths.iter().cloned().collect()
+    }
+
     pub fn unwatch(&mut self, path: &Path) -> Result<()> {
         #[allow(clippy::expect_used)]
         let _lock = self.mx.lock().expect("Watched set mutex is poisoned");
@@ -148,6 +155,7 @@ mod tests {
         let mut set = new_watched_set()?;
         set.watch(path.clone())?;
         assert!(set.paths.contains(&path));
+        assert_eq!(set.paths(), [path]);
 
         Ok(())
     }
@@ -4,6 +4,7 @@ use std::{
 };
 
 use anyhow::Result;
+use common::sync::FileFilter;
 #[cfg(not(test))]
 use paths as zed_paths;
 #[cfg(test)]
@@ -16,7 +17,10 @@ pub struct ZedConfigFilePath {
 }
 
 impl ZedConfigFilePath {
-    pub fn from_file_uri(file_uri: &Url) -> Result<Self, ZedConfigPathError> {
+    pub fn from_file_uri(
+        file_uri: &Url,
+        file_filter: &FileFilter,
+    ) -> Result<Self, ZedConfigPathError> {
         if !file_uri.scheme().eq_ignore_ascii_case("file") {
             return Err(ZedConfigPathError::WrongFileUriFormat);
         }
@@ -25,8 +29,13 @@ impl ZedConfigFilePath {
             .to_file_path()
             .map_err(|()| ZedConfigPathError::WrongFileUriFormat)?;
 
+        Self::from_path(path, file_filter)
+    }
+
+    pub fn from_path(path: PathBuf, file_filter: &FileFilter) -> Result<Self, ZedConfigPathError> {
         validate_file_path(&path)?;
         validate_file_extension(&path)?;
+        validate_sync_rules(&path, file_filter)?;
 
         Ok(Self { path })
     }
@@ -57,6 +66,18 @@ fn validate_file_path(path: &Path) -> Result<(), ZedConfigPathError> {
     }
 }
 
+fn validate_sync_rules(path: &Path, file_filter: &FileFilter) -> Result<(), ZedConfigPathError> {
+    let relative_path = path
+        .strip_prefix(zed_paths::config_dir())
+        .map_err(|_| ZedConfigPathError::NotZedConfigFile)?;
+
+    if file_filter.matches(relative_path) {
+        Ok(())
+    } else {
+        Err(ZedConfigPathError::ExcludedFromSync)
+    }
+}
+
 impl Display for ZedConfigFilePath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.path.display())
@@ -72,6 +93,7 @@ impl AsRef<Path> for ZedConfigFilePath {
 #[derive(Debug, PartialEq, Eq)]
 pub enum ZedConfigPathError {
     NotZedConfigFile,
+    ExcludedFromSync,
     WrongFileUriFormat,
 }
 
@@ -79,6 +101,7 @@ pub enum ZedConfigPathError {
 mod tests {
     #![allow(clippy::unwrap_used)]
 
+    use common::sync::FileFilter;
     use test_support::zed_paths;
     use tower_lsp::lsp_types::Url;
 
@@ -91,7 +114,8 @@ mod tests {
             zed_paths::config_dir().display()
         ))
         .unwrap();
-        let config_path = ZedConfigFilePath::from_file_uri(&file_uri).unwrap();
+        let config_path =
+            ZedConfigFilePath::from_file_uri(&file_uri, &FileFilter::default()).unwrap();
 
         assert_eq!(
             config_path.path.to_string_lossy(),
@@ -104,7 +128,7 @@ mod tests {
         let file_uri = Url::parse("lol:///home/user/.config/zed/settings.json").unwrap();
 
         assert_eq!(
-            ZedConfigFilePath::from_file_uri(&file_uri).unwrap_err(),
+            ZedConfigFilePath::from_file_uri(&file_uri, &FileFilter::default()).unwrap_err(),
             ZedConfigPathError::WrongFileUriFormat
         );
     }
@@ -118,16 +142,32 @@ mod tests {
         .unwrap();
 
         assert_eq!(
-            ZedConfigFilePath::from_file_uri(&file_uri),
+            ZedConfigFilePath::from_file_uri(&file_uri, &FileFilter::default()),
             Err(ZedConfigPathError::NotZedConfigFile)
         );
     }
 
+    #[test]
+    fn test_from_file_uri_failure_excluded_from_sync() {
+        let file_uri = Url::parse(&format!(
+            "file:///{}/debug.json",
+            zed_paths::config_dir().display()
+        ))
+        .unwrap();
+        let file_filter = FileFilter::new(&[], &["debug.json".to_string()]).unwrap();
+
+        assert_eq!(
+            ZedConfigFilePath::from_file_uri(&file_uri, &file_filter),
+            Err(ZedConfigPathError::ExcludedFromSync)
+        );
+    }
+
     #[test]
     fn test_to_watched_path_buf_success() {
         let file_uri =
             Url::parse(&format!("file:///{}", zed_paths::settings_file().display())).unwrap();
-        let config_path = ZedConfigFilePath::from_file_uri(&file_uri).unwrap();
+        let config_path =
+            ZedConfigFilePath::from_file_uri(&file_uri, &FileFilter::default()).unwrap();
 
         assert_eq!(
             config_path.to_watched_path_buf().display().to_string(),
@@ -23,6 +23,8 @@ static ZED_CONFIG_FILE_NAME: LazyLock<&str> = LazyLock::new(|| {
 });
 static ZED_CONFIG_DIR: LazyLock<TempDir> =
     LazyLock::new(|| TempDir::new().expect("Failed to create temporary Zed config directory"));
+static ZED_DATA_DIR: LazyLock<TempDir> =
+    LazyLock::new(|| TempDir::new().expect("Failed to create temporary Zed data directory"));
 static READ_PASSWORD_INPUTS_REVERSED: LazyLock<Mutex<Vec<String>>> =
     LazyLock::new(|| Mutex::new(vec![FAKE_GITHUB_TOKEN.to_string(), String::new()]));
 
@@ -32,6 +34,10 @@ pub fn zed_config_dir() -> &'static TempDir {
     &ZED_CONFIG_DIR
 }
 
+pub fn zed_data_dir() -> &'static TempDir {
+    &ZED_DATA_DIR
+}
+
 pub fn zed_settings_file() -> ChildPath {
     zed_config_dir().child(*ZED_CONFIG_FILE_NAME)
 }
@@ -49,7 +55,7 @@ pub fn read_password() -> io::Result<String> {
 pub mod zed_paths {
     use std::path::PathBuf;
 
-    use super::{ZED_CONFIG_DIR, ZED_CONFIG_FILE_NAME};
+    use super::{ZED_CONFIG_DIR, ZED_CONFIG_FILE_NAME, ZED_DATA_DIR};
 
     pub fn settings_file() -> PathBuf {
         ZED_CONFIG_DIR.path().join(*ZED_CONFIG_FILE_NAME)
@@ -58,6 +64,18 @@ pub mod zed_paths {
     pub fn config_dir() -> PathBuf {
         ZED_CONFIG_DIR.path().to_owned()
     }
+
+    pub fn data_dir() -> PathBuf {
+        ZED_DATA_DIR.path().to_owned()
+    }
+
+    pub fn extensions_dir() -> PathBuf {
+        ZED_DATA_DIR.path().join("extensions")
+    }
+
+    pub fn logs_dir() -> PathBuf {
+        ZED_DATA_DIR.path().join("logs")
+    }
 }
 
 #[macro_export]